@@ -2,3 +2,5 @@ pub mod candidate_generation;
 pub mod candidate_matching;
 pub mod config;
 pub mod graph_matching;
+#[cfg(test)]
+pub mod test_utils;
@@ -1,10 +1,25 @@
-use crate::data::{graph::Graph, utils::vertices_are_connected, vertex::Vertex};
+use crate::data::{
+    edge::Edge,
+    graph::{Graph, VertexVectorKey},
+    utils::vertices_are_connected,
+    vertex::Vertex,
+};
 use itertools::Itertools;
-use rayon::prelude::*;
 use std::{
-    collections::{HashMap, HashSet},
-    sync::{Arc, Mutex},
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver},
+    },
+    thread,
 };
+
+/// Maximum number of candidates that `stream_candidates` passes on at once
+const CANDIDATE_BATCH_SIZE: usize = 1024;
+/// Number of batches that every generator thread of `stream_candidates` generates ahead
+const BATCHES_AHEAD: usize = 2;
+
 #[derive(Debug)]
 struct GraphIdGenerator(Mutex<usize>);
 
@@ -24,16 +39,123 @@ impl GraphIdGenerator {
 pub struct Candidate {
     pub n_activity: usize,
     pub id_parent: usize,
+    /// Id of the induced candidate if the candidate belongs to its group of edge-subset variants
+    /// (the induced candidate included), `None` if the induced candidate has no variants.
+    /// A group supports a pattern at most once.
+    pub id_group: Option<usize>,
     pub graph: Graph,
 }
 
+/// Defines which object vertices are preferred if the context of a candidate is bounded.
+///
+/// Closer objects are always preferred; the policy orders the objects with the same distance to
+/// the activity core.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContextSelectionPolicy {
+    /// Objects in the order they are discovered
+    #[default]
+    Nearest,
+    /// Objects connected to the most activity vertices of the core first
+    MostShared,
+    /// Objects whose (label, vertex type) is rare across all input graphs first
+    RareLabelsFirst,
+    /// Drops objects connected to only one activity vertex of the core
+    SharedOnly,
+}
+
+/// Controls how far the context of an activity core reaches and how large it may grow.
+///
+/// The depth is the number of hops from the activity core, following outgoing edges through
+/// object vertices. With the default (depth 1 for every object type, no limits), only object
+/// vertices directly adjacent to the selected activities are added.
+///
+/// # Example
+/// ```rust
+/// // Documents (type 5) adjacent to the activities, organisations (type 6) up to two hops away,
+/// // e.g. activity -> role -> organisation, at most 10 context vertices per candidate, at most
+/// // 3 of them documents, preferring objects shared by several activities.
+/// let context = ContextConfig {
+///     depths: HashMap::from([(6, 2)]),
+///     max_vertices: Some(10),
+///     max_vertices_per_type: HashMap::from([(5, 3)]),
+///     selection_policy: ContextSelectionPolicy::MostShared,
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ContextConfig {
+    /// Maximum distance to the activity core per object vertex type; unlisted types use 1
+    pub depths: HashMap<usize, usize>,
+    /// Maximum number of context vertices per candidate, `None` means unlimited
+    pub max_vertices: Option<usize>,
+    /// Maximum number of context vertices per object vertex type; unlisted types are unlimited
+    pub max_vertices_per_type: HashMap<usize, usize>,
+    /// Order in which the context vertices are selected
+    pub selection_policy: ContextSelectionPolicy,
+}
+
+impl ContextConfig {
+    /// Returns the maximum distance to the activity core for vertices of the given type.
+    pub fn depth_of(&self, vertex_type: usize) -> usize {
+        self.depths.get(&vertex_type).copied().unwrap_or(1)
+    }
+
+    /// Returns the largest depth of all given object vertex types.
+    pub fn max_depth(&self, object_vertex_types: &[usize]) -> usize {
+        object_vertex_types
+            .iter()
+            .map(|t| self.depth_of(*t))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Counts the vertices per (label, vertex type) across all graphs if the selection policy
+    /// needs them, otherwise the result is empty.
+    fn label_frequencies(&self, graphs: &[Graph]) -> HashMap<VertexVectorKey, usize> {
+        let mut label_frequencies = HashMap::new();
+        if self.selection_policy == ContextSelectionPolicy::RareLabelsFirst {
+            for graph in graphs {
+                for (key, count) in graph.get_vertex_vector().iter() {
+                    *label_frequencies.entry(*key).or_insert(0) += count;
+                }
+            }
+        }
+        label_frequencies
+    }
+}
+
+/// Limits the number of activity vertices of one activity vertex type in a candidate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActivityTypeLimits {
+    /// Minimum number of activity vertices of this type
+    pub min: usize,
+    /// Maximum number of activity vertices of this type, `None` means unlimited
+    pub max: Option<usize>,
+}
+
+impl ActivityTypeLimits {
+    fn allows(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
 #[derive(Debug)]
 /// Enum representing different strategies for generating candidate subgraphs from a set of input graphs.
 ///
 /// Currently, it supports:
 /// - `FullyConnected`: Generates candidates where a subset of activity vertices are fully connected,
-///   optionally including connected object vertices. The number of activity vertices can be controlled
-///   with minimum and maximum limits.
+///   optionally including connected object vertices. The activity core may mix several activity
+///   vertex types. The number of activity vertices can be controlled with minimum and maximum
+///   limits, in total and per activity vertex type, the extent of the object context with a
+///   `ContextConfig`. Candidates are vertex-induced; with `max_dropped_edges > 0`, all connected
+///   spanning edge subsets that drop up to this number of edges are added as candidates, too.
+/// - `EgoNetwork`: Generates one candidate per object vertex of the `center_vertex_type`, containing
+///   the vertices of the activity and object vertex types within `radius` hops of the center
+///   (ignoring the edge direction). Candidates with fewer or more activity vertices than the
+///   minimum and maximum limits are skipped.
+/// - `DirectedPaths`: Generates candidates whose activity core follows the edge direction. Without
+///   `dags`, the cores are simple directed paths of activity vertices (A -> B -> C) that contain only
+///   the edges along the path; with `dags`, the cores are connected sets of activity vertices whose
+///   edges form a directed acyclic graph. The object context is added as for `FullyConnected`.
 ///
 /// # Example
 /// ```rust
@@ -43,167 +165,723 @@ pub struct Candidate {
 /// let graphs: Vec<Graph> = vec![/* some graphs */];
 ///
 /// let algo = AlgoCandidateGeneration::FullyConnected {
-///     activity_vertex_type: 2,
+///     activity_vertex_types: vec![2, 3],
+///     activity_type_limits: HashMap::from([(3, ActivityTypeLimits { min: 1, max: Some(1) })]),
 ///     object_vertex_types: vec![4],
+///     context: ContextConfig::default(),
 ///     min_number_of_activity_vertices: 2,
 ///     max_number_of_activity_vertices: 3,
+///     max_dropped_edges: 0,
 /// };
 ///
-/// let candidates = algo.get_candidates(&graphs);
-/// // `candidates` is a Vec<Vec<Graph>>, one Vec<Graph> per input graph
+/// algo.stream_candidates(&graphs, |batch| {
+///     for (i_n, candidate) in batch {
+///         // `i_n` is the index of the candidate size, i.e. its number of activity vertices
+///         // minus the minimum
+///     }
+/// });
 /// ```
 pub enum AlgoCandidateGeneration {
     FullyConnected {
-        activity_vertex_type: usize,
+        activity_vertex_types: Vec<usize>,
+        activity_type_limits: HashMap<usize, ActivityTypeLimits>,
+        object_vertex_types: Vec<usize>,
+        context: ContextConfig,
+        min_number_of_activity_vertices: usize,
+        max_number_of_activity_vertices: usize,
+        max_dropped_edges: usize,
+    },
+    EgoNetwork {
+        center_vertex_type: usize,
+        activity_vertex_types: Vec<usize>,
         object_vertex_types: Vec<usize>,
+        radius: usize,
         min_number_of_activity_vertices: usize,
         max_number_of_activity_vertices: usize,
     },
+    DirectedPaths {
+        activity_vertex_types: Vec<usize>,
+        object_vertex_types: Vec<usize>,
+        context: ContextConfig,
+        min_number_of_activity_vertices: usize,
+        max_number_of_activity_vertices: usize,
+        dags: bool,
+    },
 }
 
 impl AlgoCandidateGeneration {
-    /// Generates candidate subgraphs for each input graph according to the selected generation strategy.
-    ///
-    /// # Arguments
-    ///
-    /// * `graphs` - A vector of input graphs to generate candidates from.
-    ///
-    /// # Returns
+    /// Generates candidate subgraphs for each input graph according to the selected generation
+    /// strategy and passes them to `on_candidates` in batches of (size index, candidate), without
+    /// collecting them.
     ///
-    /// A `Vec<Vec<Graph>>`, where each inner vector contains candidate subgraphs generated from
-    /// the corresponding input graph.
+    /// One thread per rayon thread generates the candidates of every n-th input graph; the
+    /// batches follow the order of the input graphs. Every thread only generates a few batches
+    /// ahead, so at most some batches per thread are in memory at a time.
     ///
     /// # Notes
     ///
     /// - For `FullyConnected`, only activity vertices that are fully connected (all pairs have paths) are considered.
-    /// - Object vertices connected to selected activity vertices are included in the candidate graphs.
+    ///   Activity sets that violate the `activity_type_limits` are skipped.
+    /// - Object vertices connected to selected activity vertices are included in the candidate graphs,
+    ///   up to the depth and size limits of the `ContextConfig`.
+    /// - For `EgoNetwork`, the candidates contain all edges between their vertices.
+    /// - For `DirectedPaths`, every distinct directed path is a candidate of its own, even if
+    ///   several paths visit the same activity vertices.
     /// - Candidate graphs are assigned unique IDs automatically.
     ///
     /// # Example
     /// ```rust
-    /// let candidates = algo.get_candidates(&graphs);
-    /// assert_eq!(candidates.len(), graphs.len()); // one Vec<Graph> per input graph
+    /// let mut number_of_candidates = 0;
+    /// algo.stream_candidates(&graphs, |batch| number_of_candidates += batch.len());
     /// ```
-    pub fn get_candidates(&self, graphs: &Vec<Graph>) -> Vec<Vec<Vec<Candidate>>> {
-        let graph_id_generator = Arc::new(GraphIdGenerator::new());
+    pub fn stream_candidates<F>(&self, graphs: &[Graph], mut on_candidates: F)
+    where
+        F: FnMut(Vec<(usize, Candidate)>),
+    {
+        let graph_id_generator = GraphIdGenerator::new();
+        let label_frequencies = self.label_frequencies(graphs);
+        let number_of_threads = rayon::current_num_threads().clamp(1, graphs.len().max(1));
+        thread::scope(|scope| {
+            // Batches of the graphs of every thread; `None` ends the candidates of a graph
+            let receivers: Vec<Receiver<_>> = (0..number_of_threads)
+                .map(|i_thread| {
+                    let (sender, receiver) = mpsc::sync_channel(BATCHES_AHEAD);
+                    let label_frequencies = &label_frequencies;
+                    let graph_id_generator = &graph_id_generator;
+                    scope.spawn(move || {
+                        let send = |message| {
+                            sender
+                                .send(message)
+                                .expect("The candidate consumer stopped")
+                        };
+                        for graph in graphs.iter().skip(i_thread).step_by(number_of_threads) {
+                            let mut batch = Vec::with_capacity(CANDIDATE_BATCH_SIZE);
+                            self.get_candidates_of_graph(
+                                graph,
+                                label_frequencies,
+                                graph_id_generator,
+                                &mut |i_n, candidate| {
+                                    batch.push((i_n, candidate));
+                                    if batch.len() == CANDIDATE_BATCH_SIZE {
+                                        send(Some(std::mem::take(&mut batch)));
+                                    }
+                                },
+                            );
+                            if !batch.is_empty() {
+                                send(Some(batch));
+                            }
+                            send(None);
+                        }
+                    });
+                    receiver
+                })
+                .collect();
+            for i_graph in 0..graphs.len() {
+                let receiver = &receivers[i_graph % number_of_threads];
+                while let Some(batch) = receiver.recv().expect("A candidate generator stopped") {
+                    on_candidates(batch);
+                }
+            }
+        });
+    }
+
+    /// Counts the vertices per (label, vertex type) across all graphs if the context selection
+    /// needs them, otherwise the result is empty.
+    fn label_frequencies(&self, graphs: &[Graph]) -> HashMap<VertexVectorKey, usize> {
+        match self {
+            AlgoCandidateGeneration::FullyConnected { context, .. }
+            | AlgoCandidateGeneration::DirectedPaths { context, .. } => {
+                context.label_frequencies(graphs)
+            }
+            AlgoCandidateGeneration::EgoNetwork { .. } => HashMap::new(),
+        }
+    }
+
+    /// Passes the candidates of one input graph to `emit` with their size index
+    fn get_candidates_of_graph<F: FnMut(usize, Candidate)>(
+        &self,
+        graph: &Graph,
+        label_frequencies: &HashMap<VertexVectorKey, usize>,
+        graph_id_generator: &GraphIdGenerator,
+        emit: &mut F,
+    ) {
         match self {
             AlgoCandidateGeneration::FullyConnected {
-                activity_vertex_type,
+                activity_vertex_types,
+                activity_type_limits,
                 object_vertex_types,
+                context,
                 min_number_of_activity_vertices,
                 max_number_of_activity_vertices,
-            } => get_fully_connected_candidates(
-                graphs,
-                activity_vertex_type,
+                max_dropped_edges,
+            } => _get_fully_connected_candidates_of_graph(
+                graph,
+                activity_vertex_types,
+                activity_type_limits,
                 object_vertex_types,
+                context,
+                label_frequencies,
                 min_number_of_activity_vertices,
                 max_number_of_activity_vertices,
+                max_dropped_edges,
                 graph_id_generator,
+                emit,
             ),
-        }
-    }
-}
-
-fn get_fully_connected_candidates(
-    graphs: &Vec<Graph>,
-    activity_vertex_type: &usize,
-    object_vertex_types: &[usize],
-    min_number_of_activity_vertices: &usize,
-    max_number_of_activity_vertices: &usize,
-    graph_id_generator: Arc<GraphIdGenerator>,
-) -> Vec<Vec<Vec<Candidate>>> {
-    graphs
-        .par_iter() // Parallel processing
-        // .iter()
-        .map(|g| {
-            _get_fully_connected_candidates_of_graph(
-                g,
-                activity_vertex_type,
+            AlgoCandidateGeneration::EgoNetwork {
+                center_vertex_type,
+                activity_vertex_types,
+                object_vertex_types,
+                radius,
+                min_number_of_activity_vertices,
+                max_number_of_activity_vertices,
+            } => _get_ego_network_candidates_of_graph(
+                graph,
+                center_vertex_type,
+                activity_vertex_types,
+                object_vertex_types,
+                radius,
+                min_number_of_activity_vertices,
+                max_number_of_activity_vertices,
+                graph_id_generator,
+                emit,
+            ),
+            AlgoCandidateGeneration::DirectedPaths {
+                activity_vertex_types,
                 object_vertex_types,
+                context,
                 min_number_of_activity_vertices,
                 max_number_of_activity_vertices,
-                Arc::clone(&graph_id_generator),
-            )
-        })
-        .collect()
+                dags,
+            } => _get_directed_path_candidates_of_graph(
+                graph,
+                activity_vertex_types,
+                object_vertex_types,
+                context,
+                label_frequencies,
+                min_number_of_activity_vertices,
+                max_number_of_activity_vertices,
+                dags,
+                graph_id_generator,
+                emit,
+            ),
+        }
+    }
 }
 
-fn _get_fully_connected_candidates_of_graph(
+#[allow(clippy::too_many_arguments)]
+fn _get_fully_connected_candidates_of_graph<F: FnMut(usize, Candidate)>(
     graph: &Graph,
-    activity_vertex_type: &usize,
+    activity_vertex_types: &[usize],
+    activity_type_limits: &HashMap<usize, ActivityTypeLimits>,
     object_vertex_types: &[usize],
+    context: &ContextConfig,
+    label_frequencies: &HashMap<VertexVectorKey, usize>,
     min_number_of_activity_vertices: &usize,
     max_number_of_activity_vertices: &usize,
-    graph_id_generator: Arc<GraphIdGenerator>,
-) -> Vec<Vec<Candidate>> {
-    let activity_vertices = graph.get_vertices_by_type(*activity_vertex_type);
-    let mut candidates =
-        Vec::with_capacity(max_number_of_activity_vertices - min_number_of_activity_vertices + 1);
+    max_dropped_edges: &usize,
+    graph_id_generator: &GraphIdGenerator,
+    emit: &mut F,
+) {
+    let activity_vertices = graph.get_vertices_by_types(activity_vertex_types);
 
     // Get candidates for the requested number of activity vertices
     for number_of_activity_vertices in
         *min_number_of_activity_vertices..(max_number_of_activity_vertices + 1)
     {
-        let mut candidates_with_n = Vec::new();
         for comb in activity_vertices
             .iter()
             .combinations(number_of_activity_vertices)
         {
             let comb_ref: Vec<&Vertex> = comb.into_iter().copied().collect();
+            if !activity_type_limits_are_met(&comb_ref, activity_type_limits) {
+                continue;
+            }
             // Check if the vertices are connected
             if vertices_are_connected(&comb_ref) {
-                let mut new_candidate = Graph::new(graph_id_generator.next_id());
-                let mut vertex_id_mapping: HashMap<usize, usize> = HashMap::new();
-
-                let activity_v_ids: HashSet<usize> = comb_ref.iter().map(|v| v.id).collect();
-                // Create the activity vertices
-                for activity_vertex in comb_ref.iter() {
-                    let new_activity_vertex = new_candidate.create_vertex_with_data(
-                        activity_vertex.label,
-                        activity_vertex.vertex_type,
+                let activity_v_ids: Vec<usize> = comb_ref.iter().map(|v| v.id).collect();
+                let context_vertices = collect_context_vertices(
+                    graph,
+                    &activity_v_ids,
+                    object_vertex_types,
+                    context,
+                    label_frequencies,
+                );
+                let new_candidate = build_candidate_graph(
+                    graph,
+                    graph_id_generator.next_id(),
+                    &activity_v_ids,
+                    &context_vertices,
+                    object_vertex_types,
+                    context,
+                    |_| true,
+                );
+                let edge_subsets = connected_spanning_edge_subsets(
+                    &new_candidate,
+                    number_of_activity_vertices,
+                    *max_dropped_edges,
+                    graph_id_generator,
+                );
+                let id_group = (!edge_subsets.is_empty()).then_some(new_candidate.id);
+                for variant in std::iter::once(new_candidate).chain(edge_subsets) {
+                    emit(
+                        number_of_activity_vertices - min_number_of_activity_vertices,
+                        Candidate {
+                            n_activity: number_of_activity_vertices,
+                            id_parent: graph.id,
+                            id_group,
+                            graph: variant,
+                        },
                     );
-                    vertex_id_mapping.insert(activity_vertex.id, new_activity_vertex.id);
                 }
-                // Create object vertices and all edges
-                for activity_vertex in comb_ref.iter() {
-                    for edge in activity_vertex.edges.iter() {
-                        let to_vertex: &Vertex = graph.vertices.get(edge.to).unwrap();
-                        if object_vertex_types.contains(&to_vertex.vertex_type) {
-                            let new_object_vertex_id = match vertex_id_mapping.get(&to_vertex.id) {
-                                Some(id) => *id,
-                                None => {
-                                    let new_vertex = new_candidate.create_vertex_with_data(
-                                        to_vertex.label,
-                                        to_vertex.vertex_type,
-                                    );
-                                    vertex_id_mapping.insert(to_vertex.id, new_vertex.id);
-                                    new_vertex.id
-                                }
-                            };
-                            new_candidate
-                                .vertices
-                                .get_mut(*vertex_id_mapping.get(&activity_vertex.id).unwrap())
-                                .unwrap()
-                                .push(new_object_vertex_id, edge.e_label);
-                        } else if activity_v_ids.contains(&to_vertex.id) {
-                            new_candidate
-                                .vertices
-                                .get_mut(*vertex_id_mapping.get(&activity_vertex.id).unwrap())
-                                .unwrap()
-                                .push(vertex_id_mapping[&to_vertex.id], edge.e_label);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn _get_ego_network_candidates_of_graph<F: FnMut(usize, Candidate)>(
+    graph: &Graph,
+    center_vertex_type: &usize,
+    activity_vertex_types: &[usize],
+    object_vertex_types: &[usize],
+    radius: &usize,
+    min_number_of_activity_vertices: &usize,
+    max_number_of_activity_vertices: &usize,
+    graph_id_generator: &GraphIdGenerator,
+    emit: &mut F,
+) {
+    // Ego networks ignore the edge direction
+    let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); graph.vertices.len()];
+    for edge in graph.vertices.iter().flat_map(|v| &v.edges) {
+        neighbours[edge.from].push(edge.to);
+        neighbours[edge.to].push(edge.from);
+    }
+    let is_included = |vertex: &Vertex| {
+        activity_vertex_types.contains(&vertex.vertex_type)
+            || object_vertex_types.contains(&vertex.vertex_type)
+    };
+
+    for center in graph.get_vertices_by_type(*center_vertex_type) {
+        let mut distances: HashMap<usize, usize> = HashMap::from([(center.id, 0)]);
+        let mut queue = VecDeque::from([center.id]);
+        let mut ego_network = vec![center.id];
+        while let Some(vertex_id) = queue.pop_front() {
+            let distance = distances[&vertex_id];
+            if distance >= *radius {
+                continue;
+            }
+            for neighbour_id in neighbours[vertex_id].iter() {
+                if !distances.contains_key(neighbour_id)
+                    && is_included(&graph.vertices[*neighbour_id])
+                {
+                    distances.insert(*neighbour_id, distance + 1);
+                    ego_network.push(*neighbour_id);
+                    queue.push_back(*neighbour_id);
+                }
+            }
+        }
+
+        let number_of_activity_vertices = ego_network
+            .iter()
+            .filter(|id| activity_vertex_types.contains(&graph.vertices[**id].vertex_type))
+            .count();
+        if number_of_activity_vertices < *min_number_of_activity_vertices
+            || number_of_activity_vertices > *max_number_of_activity_vertices
+        {
+            continue;
+        }
+        emit(
+            number_of_activity_vertices - min_number_of_activity_vertices,
+            Candidate {
+                n_activity: number_of_activity_vertices,
+                id_parent: graph.id,
+                id_group: None,
+                graph: build_induced_graph(graph, graph_id_generator.next_id(), &ego_network),
+            },
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn _get_directed_path_candidates_of_graph<F: FnMut(usize, Candidate)>(
+    graph: &Graph,
+    activity_vertex_types: &[usize],
+    object_vertex_types: &[usize],
+    context: &ContextConfig,
+    label_frequencies: &HashMap<VertexVectorKey, usize>,
+    min_number_of_activity_vertices: &usize,
+    max_number_of_activity_vertices: &usize,
+    dags: &bool,
+    graph_id_generator: &GraphIdGenerator,
+    emit: &mut F,
+) {
+    let activity_vertices = graph.get_vertices_by_types(activity_vertex_types);
+
+    let mut push_candidate = |activity_v_ids: &[usize], path: bool| {
+        let context_vertices = collect_context_vertices(
+            graph,
+            activity_v_ids,
+            object_vertex_types,
+            context,
+            label_frequencies,
+        );
+        // Only the consecutive vertices of a path are linked in its core
+        let keep_core_edge = |edge: &Edge| {
+            !path
+                || activity_v_ids
+                    .windows(2)
+                    .any(|w| w[0] == edge.from && w[1] == edge.to)
+        };
+        let new_candidate = build_candidate_graph(
+            graph,
+            graph_id_generator.next_id(),
+            activity_v_ids,
+            &context_vertices,
+            object_vertex_types,
+            context,
+            keep_core_edge,
+        );
+        emit(
+            activity_v_ids.len() - min_number_of_activity_vertices,
+            Candidate {
+                n_activity: activity_v_ids.len(),
+                id_parent: graph.id,
+                id_group: None,
+                graph: new_candidate,
+            },
+        );
+    };
+
+    if *dags {
+        for number_of_activity_vertices in
+            *min_number_of_activity_vertices..(max_number_of_activity_vertices + 1)
+        {
+            for comb in activity_vertices
+                .iter()
+                .combinations(number_of_activity_vertices)
+            {
+                let comb_ref: Vec<&Vertex> = comb.into_iter().copied().collect();
+                if vertices_are_connected(&comb_ref) && vertices_are_acyclic(&comb_ref) {
+                    let activity_v_ids: Vec<usize> = comb_ref.iter().map(|v| v.id).collect();
+                    push_candidate(&activity_v_ids, false);
+                }
+            }
+        }
+    } else {
+        let is_activity: HashSet<usize> = activity_vertices.iter().map(|v| v.id).collect();
+        let mut path = Vec::with_capacity(*max_number_of_activity_vertices);
+        for start in activity_vertices.iter() {
+            path.push(start.id);
+            extend_directed_paths(
+                graph,
+                &is_activity,
+                &mut path,
+                *min_number_of_activity_vertices,
+                *max_number_of_activity_vertices,
+                &mut push_candidate,
+            );
+            path.pop();
+        }
+    }
+}
+
+/// Depth-first enumeration of all simple directed paths through activity vertices that start with
+/// `path`; `on_path` is called for each path with `min_length` to `max_length` vertices.
+fn extend_directed_paths<F: FnMut(&[usize], bool)>(
+    graph: &Graph,
+    is_activity: &HashSet<usize>,
+    path: &mut Vec<usize>,
+    min_length: usize,
+    max_length: usize,
+    on_path: &mut F,
+) {
+    if path.len() >= min_length {
+        on_path(path, true);
+    }
+    if path.len() >= max_length {
+        return;
+    }
+    let last = *path.last().unwrap();
+    let successors: Vec<usize> = graph.vertices[last]
+        .edges
+        .iter()
+        .map(|e| e.to)
+        .filter(|to| is_activity.contains(to) && !path.contains(to))
+        .unique()
+        .collect();
+    for successor in successors {
+        path.push(successor);
+        extend_directed_paths(graph, is_activity, path, min_length, max_length, on_path);
+        path.pop();
+    }
+}
+
+/// Checks that the edges between the given vertices contain no directed cycle (Kahn's algorithm).
+fn vertices_are_acyclic(vertices: &[&Vertex]) -> bool {
+    let v_ids: HashSet<usize> = vertices.iter().map(|v| v.id).collect();
+    let mut in_degrees: HashMap<usize, usize> = v_ids.iter().map(|id| (*id, 0)).collect();
+    for edge in vertices.iter().flat_map(|v| &v.edges) {
+        if v_ids.contains(&edge.to) {
+            *in_degrees.get_mut(&edge.to).unwrap() += 1;
+        }
+    }
+    let mut queue: VecDeque<&Vertex> = vertices
+        .iter()
+        .filter(|v| in_degrees[&v.id] == 0)
+        .copied()
+        .collect();
+    let mut number_of_sorted = 0;
+    while let Some(vertex) = queue.pop_front() {
+        number_of_sorted += 1;
+        for edge in vertex.edges.iter().filter(|e| v_ids.contains(&e.to)) {
+            let in_degree = in_degrees.get_mut(&edge.to).unwrap();
+            *in_degree -= 1;
+            if *in_degree == 0 {
+                queue.push_back(vertices.iter().find(|v| v.id == edge.to).unwrap());
+            }
+        }
+    }
+    number_of_sorted == vertices.len()
+}
+
+/// Copies the given vertices of `graph` and all edges between them into a new candidate graph.
+fn build_induced_graph(graph: &Graph, candidate_id: usize, vertex_ids: &[usize]) -> Graph {
+    let mut new_candidate = Graph::new(candidate_id);
+    let mut vertex_id_mapping: HashMap<usize, usize> = HashMap::new();
+    for vertex_id in vertex_ids.iter() {
+        let vertex = &graph.vertices[*vertex_id];
+        let new_vertex = new_candidate.create_vertex_with_data(vertex.label, vertex.vertex_type);
+        vertex_id_mapping.insert(*vertex_id, new_vertex.id);
+    }
+    for vertex_id in vertex_ids.iter() {
+        let new_vertex_id = vertex_id_mapping[vertex_id];
+        for edge in graph.vertices[*vertex_id].edges.iter() {
+            if let Some(new_to_id) = vertex_id_mapping.get(&edge.to) {
+                new_candidate.vertices[new_vertex_id].push(*new_to_id, edge.e_label);
+            }
+        }
+    }
+    new_candidate
+}
+
+/// Checks the number of activity vertices per activity vertex type against the configured limits.
+fn activity_type_limits_are_met(
+    activity_vertices: &[&Vertex],
+    activity_type_limits: &HashMap<usize, ActivityTypeLimits>,
+) -> bool {
+    activity_type_limits.iter().all(|(vertex_type, limits)| {
+        let count = activity_vertices
+            .iter()
+            .filter(|v| v.vertex_type == *vertex_type)
+            .count();
+        limits.allows(count)
+    })
+}
+
+/// Collects the object vertices that form the context of an activity core.
+///
+/// Starting from the activity vertices, outgoing edges are followed breadth-first through object
+/// vertices. An object vertex is eligible if its type is one of `object_vertex_types` and its
+/// distance to the core does not exceed the configured depth of its type.
+///
+/// The eligible vertices are selected layer by layer, so closer objects always win. Within a
+/// layer, the `ContextSelectionPolicy` defines the order. A vertex is only selected if it is still
+/// reachable through a selected vertex of the previous layer and the size limits (in total and per
+/// vertex type) are not exceeded.
+///
+/// Returns the ids of the context vertices (in selection order) together with their distance to
+/// the activity core.
+fn collect_context_vertices(
+    graph: &Graph,
+    activity_v_ids: &[usize],
+    object_vertex_types: &[usize],
+    context: &ContextConfig,
+    label_frequencies: &HashMap<VertexVectorKey, usize>,
+) -> Vec<(usize, usize)> {
+    let max_depth = context.max_depth(object_vertex_types);
+    let mut distances: HashMap<usize, usize> = activity_v_ids.iter().map(|id| (*id, 0)).collect();
+    let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+    // Activity vertices of the core that reach a vertex
+    let mut supporters: HashMap<usize, HashSet<usize>> = activity_v_ids
+        .iter()
+        .map(|id| (*id, HashSet::from([*id])))
+        .collect();
+    let mut layers: Vec<Vec<usize>> = vec![activity_v_ids.to_vec()];
+
+    while layers.len() <= max_depth {
+        let distance = layers.len() - 1;
+        let mut next_layer = Vec::new();
+        for vertex_id in layers[distance].iter() {
+            for edge in graph.vertices[*vertex_id].edges.iter() {
+                let to_vertex = &graph.vertices[edge.to];
+                match distances.get(&edge.to) {
+                    Some(to_distance) if *to_distance != distance + 1 => continue,
+                    Some(_) => {}
+                    None => {
+                        if !object_vertex_types.contains(&to_vertex.vertex_type)
+                            || context.depth_of(to_vertex.vertex_type) <= distance
+                        {
+                            continue;
                         }
+                        distances.insert(edge.to, distance + 1);
+                        next_layer.push(edge.to);
                     }
                 }
-                candidates_with_n.push(Candidate {
-                    n_activity: number_of_activity_vertices,
-                    id_parent: graph.id,
-                    graph: new_candidate,
-                })
+                predecessors.entry(edge.to).or_default().push(*vertex_id);
+                let reached_by = supporters[vertex_id].clone();
+                supporters.entry(edge.to).or_default().extend(reached_by);
+            }
+        }
+        if next_layer.is_empty() {
+            break;
+        }
+        layers.push(next_layer);
+    }
+
+    let mut selected: HashSet<usize> = activity_v_ids.iter().copied().collect();
+    let mut selected_per_type: HashMap<usize, usize> = HashMap::new();
+    let mut context_vertices = Vec::new();
+    for (distance, layer) in layers.iter_mut().enumerate().skip(1) {
+        match context.selection_policy {
+            ContextSelectionPolicy::Nearest | ContextSelectionPolicy::SharedOnly => {}
+            ContextSelectionPolicy::MostShared => {
+                layer.sort_by_key(|id| Reverse(supporters[id].len()));
+            }
+            ContextSelectionPolicy::RareLabelsFirst => layer.sort_by_key(|id| {
+                let vertex = &graph.vertices[*id];
+                label_frequencies
+                    .get(&(vertex.label, vertex.vertex_type))
+                    .copied()
+                    .unwrap_or(0)
+            }),
+        }
+        for vertex_id in layer.iter() {
+            if let Some(max_vertices) = context.max_vertices
+                && context_vertices.len() >= max_vertices
+            {
+                return context_vertices;
+            }
+            if context.selection_policy == ContextSelectionPolicy::SharedOnly
+                && supporters[vertex_id].len() < 2
+            {
+                continue;
+            }
+            if !predecessors[vertex_id].iter().any(|p| selected.contains(p)) {
+                continue;
+            }
+            let vertex_type = graph.vertices[*vertex_id].vertex_type;
+            let count = selected_per_type.entry(vertex_type).or_insert(0);
+            if let Some(max_of_type) = context.max_vertices_per_type.get(&vertex_type)
+                && *count >= *max_of_type
+            {
+                continue;
+            }
+            *count += 1;
+            selected.insert(*vertex_id);
+            context_vertices.push((*vertex_id, distance));
+        }
+    }
+    context_vertices
+}
+
+/// Copies the activity core and its context vertices of `graph` into a new candidate graph.
+///
+/// All outgoing edges of the activity vertices that point to another vertex of the candidate are
+/// copied, except for edges between two activity vertices that `keep_core_edge` rejects. An outgoing
+/// edge of a context vertex is only copied if the context vertex lies closer to the core than the
+/// configured depth of the edge's target type, i.e. if the edge could have been used to reach the
+/// target. Edges back into the core use the largest depth of all object vertex types.
+fn build_candidate_graph<F: Fn(&Edge) -> bool>(
+    graph: &Graph,
+    candidate_id: usize,
+    activity_v_ids: &[usize],
+    context_vertices: &[(usize, usize)],
+    object_vertex_types: &[usize],
+    context: &ContextConfig,
+    keep_core_edge: F,
+) -> Graph {
+    let max_depth = context.max_depth(object_vertex_types);
+    let mut new_candidate = Graph::new(candidate_id);
+    let mut vertex_id_mapping: HashMap<usize, usize> = HashMap::new();
+
+    let included = activity_v_ids
+        .iter()
+        .map(|id| (*id, 0))
+        .chain(context_vertices.iter().copied());
+    for (vertex_id, _) in included.clone() {
+        let vertex = &graph.vertices[vertex_id];
+        let new_vertex = new_candidate.create_vertex_with_data(vertex.label, vertex.vertex_type);
+        vertex_id_mapping.insert(vertex_id, new_vertex.id);
+    }
+    for (vertex_id, distance) in included {
+        if distance > 0 && distance >= max_depth {
+            continue;
+        }
+        let new_vertex_id = vertex_id_mapping[&vertex_id];
+        for edge in graph.vertices[vertex_id].edges.iter() {
+            let to_core = activity_v_ids.contains(&edge.to);
+            if distance == 0 && to_core && !keep_core_edge(edge) {
+                continue;
+            }
+            if distance > 0
+                && !to_core
+                && distance >= context.depth_of(graph.vertices[edge.to].vertex_type)
+            {
+                continue;
+            }
+            if let Some(new_to_id) = vertex_id_mapping.get(&edge.to) {
+                new_candidate.vertices[new_vertex_id].push(*new_to_id, edge.e_label);
             }
         }
-        candidates.push(candidates_with_n);
     }
-    candidates
+    new_candidate
+}
+
+/// Enumerates the non-induced variants of a candidate graph.
+///
+/// Every variant keeps all vertices but drops between one and `max_dropped_edges` edges, as long
+/// as the variant stays connected and its activity core (the first `n_activity` vertices) stays
+/// connected, too. Dropping one of several identical parallel edges results in the same graph, so
+/// each combination of dropped (from, to, label) edges is only used once.
+fn connected_spanning_edge_subsets(
+    candidate: &Graph,
+    n_activity: usize,
+    max_dropped_edges: usize,
+    graph_id_generator: &GraphIdGenerator,
+) -> Vec<Graph> {
+    let edges: Vec<&Edge> = candidate.vertices.iter().flat_map(|v| &v.edges).collect();
+    let mut subsets = Vec::new();
+    let mut seen_dropped: HashSet<Vec<(usize, usize, usize)>> = HashSet::new();
+
+    for number_of_dropped_edges in 1..=max_dropped_edges.min(edges.len()) {
+        for dropped in edges.iter().combinations(number_of_dropped_edges) {
+            let mut dropped_keys: Vec<(usize, usize, usize)> =
+                dropped.iter().map(|e| (e.from, e.to, e.e_label)).collect();
+            dropped_keys.sort_unstable();
+            if seen_dropped.contains(&dropped_keys) {
+                continue;
+            }
+            let dropped_ids: HashSet<usize> = dropped.iter().map(|e| e.id).collect();
+
+            let mut edge_subset = Graph::new(usize::MAX);
+            for vertex in candidate.vertices.iter() {
+                edge_subset.create_vertex_with_data(vertex.label, vertex.vertex_type);
+            }
+            for edge in edges.iter().filter(|e| !dropped_ids.contains(&e.id)) {
+                edge_subset.vertices[edge.from].push(edge.to, edge.e_label);
+            }
+            let activity_vertices: Vec<&Vertex> =
+                edge_subset.vertices[..n_activity].iter().collect();
+            if vertices_are_connected(&edge_subset.vertices.iter().collect())
+                && vertices_are_connected(&activity_vertices)
+            {
+                edge_subset.id = graph_id_generator.next_id();
+                subsets.push(edge_subset);
+            }
+            seen_dropped.insert(dropped_keys);
+        }
+    }
+    subsets
 }
 
 #[cfg(test)]
@@ -211,6 +889,47 @@ mod tests {
     use super::*;
     use crate::data::graph::Graph;
 
+    /// Candidates of every graph (graph -> size index -> candidates)
+    fn get_candidates(
+        algo: &AlgoCandidateGeneration,
+        graphs: &[Graph],
+    ) -> Vec<Vec<Vec<Candidate>>> {
+        let (AlgoCandidateGeneration::FullyConnected {
+            min_number_of_activity_vertices,
+            max_number_of_activity_vertices,
+            ..
+        }
+        | AlgoCandidateGeneration::EgoNetwork {
+            min_number_of_activity_vertices,
+            max_number_of_activity_vertices,
+            ..
+        }
+        | AlgoCandidateGeneration::DirectedPaths {
+            min_number_of_activity_vertices,
+            max_number_of_activity_vertices,
+            ..
+        }) = algo;
+        let graph_id_generator = GraphIdGenerator::new();
+        let label_frequencies = algo.label_frequencies(graphs);
+        graphs
+            .iter()
+            .map(|graph| {
+                let mut candidates =
+                    vec![
+                        Vec::new();
+                        max_number_of_activity_vertices - min_number_of_activity_vertices + 1
+                    ];
+                algo.get_candidates_of_graph(
+                    graph,
+                    &label_frequencies,
+                    &graph_id_generator,
+                    &mut |i_n, candidate| candidates[i_n].push(candidate),
+                );
+                candidates
+            })
+            .collect()
+    }
+
     fn make_basic_graph() -> Graph {
         let mut g = Graph::new(1);
 
@@ -238,13 +957,16 @@ mod tests {
         let g = make_basic_graph();
 
         let algo = AlgoCandidateGeneration::FullyConnected {
-            activity_vertex_type: 2,
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
             object_vertex_types: vec![4],
+            context: ContextConfig::default(),
             min_number_of_activity_vertices: 2,
             max_number_of_activity_vertices: 2,
+            max_dropped_edges: 0,
         };
 
-        let result = algo.get_candidates(&vec![g]);
+        let result = get_candidates(&algo, &[g]);
 
         assert_eq!(result.len(), 1);
         assert_eq!(result[0][0].len(), 2);
@@ -259,13 +981,16 @@ mod tests {
         g.create_vertex_with_data(2, 2); // id 1
 
         let algo = AlgoCandidateGeneration::FullyConnected {
-            activity_vertex_type: 2,
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
             object_vertex_types: vec![],
+            context: ContextConfig::default(),
             min_number_of_activity_vertices: 2,
             max_number_of_activity_vertices: 2,
+            max_dropped_edges: 0,
         };
 
-        let result = algo.get_candidates(&vec![g]);
+        let result = get_candidates(&algo, &[g]);
         assert_eq!(
             result[0][0].len(),
             0,
@@ -278,13 +1003,16 @@ mod tests {
         let g = make_basic_graph();
 
         let algo = AlgoCandidateGeneration::FullyConnected {
-            activity_vertex_type: 2,
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
             object_vertex_types: vec![4],
+            context: ContextConfig::default(),
             min_number_of_activity_vertices: 2,
             max_number_of_activity_vertices: 2,
+            max_dropped_edges: 0,
         };
 
-        let result = algo.get_candidates(&vec![g]);
+        let result = get_candidates(&algo, &[g]);
         let candidates = &result[0][0];
 
         let candidate: &Graph = candidates
@@ -304,13 +1032,16 @@ mod tests {
         let g = make_basic_graph();
 
         let algo = AlgoCandidateGeneration::FullyConnected {
-            activity_vertex_type: 2,
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
             object_vertex_types: vec![],
+            context: ContextConfig::default(),
             min_number_of_activity_vertices: 3,
             max_number_of_activity_vertices: 3,
+            max_dropped_edges: 0,
         };
 
-        let result = algo.get_candidates(&vec![g]);
+        let result = get_candidates(&algo, &[g]);
 
         assert_eq!(result[0][0].len(), 1);
     }
@@ -321,31 +1052,84 @@ mod tests {
         let g2 = make_basic_graph();
 
         let algo = AlgoCandidateGeneration::FullyConnected {
-            activity_vertex_type: 2,
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
             object_vertex_types: vec![4],
+            context: ContextConfig::default(),
             min_number_of_activity_vertices: 2,
             max_number_of_activity_vertices: 2,
+            max_dropped_edges: 0,
         };
 
-        let result = algo.get_candidates(&vec![g1, g2]);
+        let result = get_candidates(&algo, &[g1, g2]);
 
         assert_eq!(result.len(), 2);
         assert_eq!(result[0][0].len(), 2);
         assert_eq!(result[1][0].len(), 2);
     }
 
+    #[test]
+    fn test_stream_candidates_in_graph_order() {
+        let graphs: Vec<Graph> = (0..5)
+            .map(|id| {
+                let mut g = make_multi_hop_graph();
+                g.id = id;
+                g
+            })
+            .collect();
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
+            object_vertex_types: vec![4],
+            context: ContextConfig::default(),
+            min_number_of_activity_vertices: 1,
+            max_number_of_activity_vertices: 3,
+            max_dropped_edges: 0,
+        };
+
+        let mut streamed = Vec::new();
+        algo.stream_candidates(&graphs, |batch| {
+            assert!(!batch.is_empty() && batch.len() <= CANDIDATE_BATCH_SIZE);
+            streamed.extend(batch.into_iter().map(|(i_n, candidate)| {
+                (i_n, candidate.id_parent, candidate.graph.vertices.len())
+            }));
+        });
+
+        // Every graph is generated in one go, so the order within a graph is the emission order
+        let expected: Vec<_> = graphs
+            .iter()
+            .flat_map(|graph| {
+                let mut candidates = Vec::new();
+                algo.get_candidates_of_graph(
+                    graph,
+                    &algo.label_frequencies(&graphs),
+                    &GraphIdGenerator::new(),
+                    &mut |i_n, candidate: Candidate| {
+                        candidates.push((i_n, candidate.id_parent, candidate.graph.vertices.len()))
+                    },
+                );
+                candidates
+            })
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(streamed, expected);
+    }
+
     #[test]
     fn test_graph_id_generation_increments() {
         let g = make_basic_graph();
 
         let algo = AlgoCandidateGeneration::FullyConnected {
-            activity_vertex_type: 2,
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
             object_vertex_types: vec![4],
+            context: ContextConfig::default(),
             min_number_of_activity_vertices: 2,
             max_number_of_activity_vertices: 2,
+            max_dropped_edges: 0,
         };
 
-        let result = algo.get_candidates(&vec![g]);
+        let result = get_candidates(&algo, &[g]);
 
         let ids: Vec<_> = result[0][0].iter().map(|c| c.graph.id).collect();
         let mut sorted = ids.clone();
@@ -357,4 +1141,465 @@ mod tests {
             "Each ID must be unique and increasing"
         );
     }
+
+    fn make_multi_hop_graph() -> Graph {
+        let mut g = make_basic_graph();
+
+        // Role (type 5) of activity 0, department (type 6) of the role
+        g.create_vertex_with_data(5, 5); // id 4
+        g.create_vertex_with_data(6, 6); // id 5
+        g.vertices.get_mut(0).unwrap().push(4, 30);
+        g.vertices.get_mut(4).unwrap().push(5, 40);
+
+        g
+    }
+
+    #[test]
+    fn test_context_depth_per_object_type() {
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
+            object_vertex_types: vec![4, 5, 6],
+            context: ContextConfig::default(),
+            min_number_of_activity_vertices: 3,
+            max_number_of_activity_vertices: 3,
+            max_dropped_edges: 0,
+        };
+        let result = get_candidates(&algo, &[make_multi_hop_graph()]);
+        let candidate = &result[0][0][0].graph;
+        assert_eq!(candidate.vertices.len(), 5);
+        assert!(!candidate.vertices.iter().any(|v| v.vertex_type == 6));
+
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
+            object_vertex_types: vec![4, 5, 6],
+            context: ContextConfig {
+                depths: HashMap::from([(6, 2)]),
+                ..Default::default()
+            },
+            min_number_of_activity_vertices: 3,
+            max_number_of_activity_vertices: 3,
+            max_dropped_edges: 0,
+        };
+        let result = get_candidates(&algo, &[make_multi_hop_graph()]);
+        let candidate = &result[0][0][0].graph;
+        assert_eq!(candidate.vertices.len(), 6);
+        let department = candidate
+            .vertices
+            .iter()
+            .find(|v| v.vertex_type == 6)
+            .unwrap();
+        let role = candidate
+            .vertices
+            .iter()
+            .find(|v| v.vertex_type == 5)
+            .unwrap();
+        assert!(
+            role.edges.iter().any(|e| e.to == department.id),
+            "The role must be linked to the department"
+        );
+    }
+
+    #[test]
+    fn test_context_mixed_depths_copy_edges() {
+        // Documents (type 5) adjacent to the activities, organisations (type 6) up to two hops away
+        let mut g = Graph::new(1);
+        g.create_vertex_with_data(1, 2); // id 0
+        g.create_vertex_with_data(2, 2); // id 1
+        g.create_vertex_with_data(3, 5); // id 2, document of activity 0
+        g.create_vertex_with_data(4, 5); // id 3, document of activity 1
+        g.create_vertex_with_data(5, 6); // id 4, organisation of activity 0
+        g.create_vertex_with_data(6, 6); // id 5, parent organisation
+        g.vertices.get_mut(0).unwrap().push(1, 10);
+        g.vertices.get_mut(0).unwrap().push(2, 20);
+        g.vertices.get_mut(1).unwrap().push(3, 20);
+        g.vertices.get_mut(2).unwrap().push(3, 30);
+        g.vertices.get_mut(0).unwrap().push(4, 40);
+        g.vertices.get_mut(4).unwrap().push(5, 50);
+
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
+            object_vertex_types: vec![5, 6],
+            context: ContextConfig {
+                depths: HashMap::from([(5, 1), (6, 2)]),
+                ..Default::default()
+            },
+            min_number_of_activity_vertices: 2,
+            max_number_of_activity_vertices: 2,
+            max_dropped_edges: 0,
+        };
+        let result = get_candidates(&algo, &[g]);
+        let candidate = &result[0][0][0].graph;
+        assert_eq!(candidate.vertices.len(), 6);
+        let position_of = |label: usize| candidate.vertices.iter().position(|v| v.label == label);
+        let (document, organisation) = (position_of(3).unwrap(), position_of(5).unwrap());
+        assert!(
+            candidate.vertices[organisation]
+                .edges
+                .iter()
+                .any(|e| e.to == position_of(6).unwrap()),
+            "The organisation must be linked to its parent organisation"
+        );
+        assert!(
+            candidate.vertices[document].edges.is_empty(),
+            "Documents lie at their maximum depth, so their edges are not copied"
+        );
+    }
+
+    #[test]
+    fn test_context_max_vertices() {
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
+            object_vertex_types: vec![4, 5, 6],
+            context: ContextConfig {
+                depths: HashMap::from([(6, 2)]),
+                max_vertices: Some(2),
+                ..Default::default()
+            },
+            min_number_of_activity_vertices: 3,
+            max_number_of_activity_vertices: 3,
+            max_dropped_edges: 0,
+        };
+        let result = get_candidates(&algo, &[make_multi_hop_graph()]);
+        let candidate = &result[0][0][0].graph;
+        assert_eq!(candidate.vertices.len(), 5);
+        assert!(
+            !candidate.vertices.iter().any(|v| v.vertex_type == 6),
+            "Closer context vertices must be preferred"
+        );
+    }
+
+    fn make_mixed_activity_graph() -> Graph {
+        let mut g = make_basic_graph();
+
+        // Automated service call (type 3) connected to activity 2
+        g.create_vertex_with_data(7, 3); // id 4
+        g.vertices.get_mut(2).unwrap().push(4, 10);
+        g.vertices.get_mut(4).unwrap().push(3, 20);
+
+        g
+    }
+
+    #[test]
+    fn test_multiple_activity_vertex_types() {
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2, 3],
+            activity_type_limits: HashMap::new(),
+            object_vertex_types: vec![4],
+            context: ContextConfig::default(),
+            min_number_of_activity_vertices: 2,
+            max_number_of_activity_vertices: 2,
+            max_dropped_edges: 0,
+        };
+
+        let result = get_candidates(&algo, &[make_mixed_activity_graph()]);
+
+        // (0, 1), (1, 2) and the mixed core (2, 4)
+        assert_eq!(result[0][0].len(), 3);
+        assert!(result[0][0].iter().any(|c| {
+            c.graph.vertices.iter().any(|v| v.vertex_type == 2)
+                && c.graph.vertices.iter().any(|v| v.vertex_type == 3)
+        }));
+    }
+
+    #[test]
+    fn test_activity_type_limits() {
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2, 3],
+            activity_type_limits: HashMap::from([(
+                3,
+                ActivityTypeLimits {
+                    min: 1,
+                    max: Some(1),
+                },
+            )]),
+            object_vertex_types: vec![4],
+            context: ContextConfig::default(),
+            min_number_of_activity_vertices: 2,
+            max_number_of_activity_vertices: 3,
+            max_dropped_edges: 0,
+        };
+
+        let result = get_candidates(&algo, &[make_mixed_activity_graph()]);
+
+        // Size 2: (2, 4); size 3: (0, 2, 4) is not connected, (1, 2, 4) is
+        assert_eq!(result[0][0].len(), 1);
+        assert_eq!(result[0][1].len(), 1);
+        for candidate in result[0].iter().flatten() {
+            assert_eq!(
+                candidate
+                    .graph
+                    .vertices
+                    .iter()
+                    .filter(|v| v.vertex_type == 3)
+                    .count(),
+                1
+            );
+        }
+
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2, 3],
+            activity_type_limits: HashMap::from([(3, ActivityTypeLimits { min: 0, max: None })]),
+            object_vertex_types: vec![4],
+            context: ContextConfig::default(),
+            min_number_of_activity_vertices: 3,
+            max_number_of_activity_vertices: 3,
+            max_dropped_edges: 0,
+        };
+        let result = get_candidates(&algo, &[make_mixed_activity_graph()]);
+        assert_eq!(result[0][0].len(), 2);
+    }
+
+    #[test]
+    fn test_non_induced_edge_subsets() {
+        let mut g = Graph::new(1);
+        g.create_vertex_with_data(1, 2); // id 0
+        g.create_vertex_with_data(2, 2); // id 1
+        g.create_vertex_with_data(4, 4); // id 2
+        g.vertices.get_mut(0).unwrap().push(1, 10);
+        g.vertices.get_mut(1).unwrap().push(0, 11);
+        g.vertices.get_mut(0).unwrap().push(2, 20);
+        g.vertices.get_mut(1).unwrap().push(2, 20);
+
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
+            object_vertex_types: vec![4],
+            context: ContextConfig::default(),
+            min_number_of_activity_vertices: 2,
+            max_number_of_activity_vertices: 2,
+            max_dropped_edges: 2,
+        };
+        let result = get_candidates(&algo, &[g]);
+        let edge_counts: Vec<usize> = result[0][0]
+            .iter()
+            .map(|c| c.graph.vertices.iter().map(|v| v.edges.len()).sum())
+            .collect();
+
+        // Induced candidate with 4 edges, each single edge can be dropped, and the pairs
+        // {0->1, 1->0} would split the core, {0->2, 1->2} would isolate the object
+        assert_eq!(edge_counts.iter().filter(|c| **c == 4).count(), 1);
+        assert_eq!(edge_counts.iter().filter(|c| **c == 3).count(), 4);
+        assert_eq!(edge_counts.iter().filter(|c| **c == 2).count(), 4);
+        assert_eq!(edge_counts.len(), 9);
+
+        let ids: HashSet<usize> = result[0][0].iter().map(|c| c.graph.id).collect();
+        assert_eq!(ids.len(), 9, "Each edge subset must get its own id");
+        assert!(
+            result[0][0]
+                .iter()
+                .all(|c| c.id_group == Some(result[0][0][0].graph.id)),
+            "All variants belong to the group of the induced candidate"
+        );
+    }
+
+    fn make_shared_objects_graph() -> Graph {
+        let mut g = Graph::new(1);
+        g.create_vertex_with_data(1, 2); // id 0
+        g.create_vertex_with_data(2, 2); // id 1
+        g.create_vertex_with_data(10, 4); // id 2, object of activity 0
+        g.create_vertex_with_data(11, 4); // id 3, object of both activities
+        g.create_vertex_with_data(12, 4); // id 4, object of activity 1
+        g.create_vertex_with_data(13, 5); // id 5, object of activity 0
+        g.vertices.get_mut(0).unwrap().push(1, 0);
+        g.vertices.get_mut(0).unwrap().push(2, 0);
+        g.vertices.get_mut(0).unwrap().push(3, 0);
+        g.vertices.get_mut(0).unwrap().push(5, 0);
+        g.vertices.get_mut(1).unwrap().push(3, 0);
+        g.vertices.get_mut(1).unwrap().push(4, 0);
+
+        // Unconnected objects that make all labels except 12 more frequent
+        g.create_vertex_with_data(10, 4);
+        g.create_vertex_with_data(11, 4);
+        g.create_vertex_with_data(13, 5);
+
+        g
+    }
+
+    fn context_labels(context: ContextConfig) -> Vec<usize> {
+        let algo = AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: vec![2],
+            activity_type_limits: HashMap::new(),
+            object_vertex_types: vec![4, 5],
+            context,
+            min_number_of_activity_vertices: 2,
+            max_number_of_activity_vertices: 2,
+            max_dropped_edges: 0,
+        };
+        let result = get_candidates(&algo, &[make_shared_objects_graph()]);
+        let mut labels: Vec<usize> = result[0][0][0]
+            .graph
+            .vertices
+            .iter()
+            .filter(|v| v.vertex_type != 2)
+            .map(|v| v.label)
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    #[test]
+    fn test_context_selection_policies() {
+        let max_one = |selection_policy| ContextConfig {
+            max_vertices: Some(1),
+            selection_policy,
+            ..Default::default()
+        };
+        assert_eq!(
+            context_labels(max_one(ContextSelectionPolicy::Nearest)),
+            vec![10]
+        );
+        assert_eq!(
+            context_labels(max_one(ContextSelectionPolicy::MostShared)),
+            vec![11]
+        );
+        assert_eq!(
+            context_labels(max_one(ContextSelectionPolicy::RareLabelsFirst)),
+            vec![12]
+        );
+        assert_eq!(
+            context_labels(ContextConfig {
+                selection_policy: ContextSelectionPolicy::SharedOnly,
+                ..Default::default()
+            }),
+            vec![11]
+        );
+    }
+
+    #[test]
+    fn test_context_max_vertices_per_type() {
+        assert_eq!(
+            context_labels(ContextConfig::default()),
+            vec![10, 11, 12, 13]
+        );
+        assert_eq!(
+            context_labels(ContextConfig {
+                max_vertices_per_type: HashMap::from([(4, 2)]),
+                ..Default::default()
+            }),
+            vec![10, 11, 13]
+        );
+    }
+
+    #[test]
+    fn test_ego_network_candidates() {
+        let mut g = make_basic_graph();
+        // Second object (type 4) only connected to activity 2
+        g.create_vertex_with_data(5, 4); // id 4
+        g.vertices.get_mut(2).unwrap().push(4, 20);
+
+        let algo = AlgoCandidateGeneration::EgoNetwork {
+            center_vertex_type: 4,
+            activity_vertex_types: vec![2],
+            object_vertex_types: vec![4],
+            radius: 1,
+            min_number_of_activity_vertices: 1,
+            max_number_of_activity_vertices: 3,
+        };
+        let result = get_candidates(&algo, &[g.clone()]);
+
+        // Object 3 touches all three activities, object 4 only activity 2
+        assert_eq!(result[0].len(), 3);
+        assert_eq!(result[0][0].len(), 1);
+        assert_eq!(result[0][1].len(), 0);
+        assert_eq!(result[0][2].len(), 1);
+        let center_3 = &result[0][2][0].graph;
+        assert_eq!(center_3.vertices.len(), 4);
+        assert_eq!(center_3.vertices[0].label, 4);
+        // Edges between the activities are part of the ego network
+        assert_eq!(
+            center_3
+                .vertices
+                .iter()
+                .map(|v| v.edges.len())
+                .sum::<usize>(),
+            5
+        );
+
+        // With radius 2, the ego network of object 4 also reaches activity 1 and object 3
+        let algo = AlgoCandidateGeneration::EgoNetwork {
+            center_vertex_type: 4,
+            activity_vertex_types: vec![2],
+            object_vertex_types: vec![4],
+            radius: 2,
+            min_number_of_activity_vertices: 2,
+            max_number_of_activity_vertices: 2,
+        };
+        let result = get_candidates(&algo, &[g]);
+        assert_eq!(result[0][0].len(), 1);
+        let center_4 = &result[0][0][0].graph;
+        assert_eq!(center_4.vertices[0].label, 5);
+        assert_eq!(center_4.vertices.len(), 4);
+    }
+
+    fn make_directed_graph() -> Graph {
+        let mut g = Graph::new(1);
+        g.create_vertex_with_data(1, 2); // id 0: A
+        g.create_vertex_with_data(2, 2); // id 1: B
+        g.create_vertex_with_data(3, 2); // id 2: C
+        g.create_vertex_with_data(4, 4); // id 3: object of B
+        g.vertices.get_mut(0).unwrap().push(1, 10); // A -> B
+        g.vertices.get_mut(1).unwrap().push(2, 10); // B -> C
+        g.vertices.get_mut(0).unwrap().push(2, 11); // A -> C
+        g.vertices.get_mut(1).unwrap().push(3, 20);
+        g
+    }
+
+    #[test]
+    fn test_directed_path_candidates() {
+        let algo = AlgoCandidateGeneration::DirectedPaths {
+            activity_vertex_types: vec![2],
+            object_vertex_types: vec![4],
+            context: ContextConfig::default(),
+            min_number_of_activity_vertices: 2,
+            max_number_of_activity_vertices: 3,
+            dags: false,
+        };
+        let result = get_candidates(&algo, &[make_directed_graph()]);
+
+        // A -> B, B -> C, A -> C and A -> B -> C
+        assert_eq!(result[0][0].len(), 3);
+        assert_eq!(result[0][1].len(), 1);
+        let path = &result[0][1][0].graph;
+        assert_eq!(path.vertices.len(), 4);
+        // The shortcut A -> C is not part of the path, the object edge is
+        assert_eq!(
+            path.vertices.iter().map(|v| v.edges.len()).sum::<usize>(),
+            3
+        );
+        assert!(path.vertices.iter().any(|v| v.vertex_type == 4));
+    }
+
+    #[test]
+    fn test_directed_dag_candidates() {
+        let mut g = make_directed_graph();
+        let algo = AlgoCandidateGeneration::DirectedPaths {
+            activity_vertex_types: vec![2],
+            object_vertex_types: vec![4],
+            context: ContextConfig::default(),
+            min_number_of_activity_vertices: 3,
+            max_number_of_activity_vertices: 3,
+            dags: true,
+        };
+        let result = get_candidates(&algo, &[g.clone()]);
+        assert_eq!(result[0][0].len(), 1);
+        assert_eq!(
+            result[0][0][0]
+                .graph
+                .vertices
+                .iter()
+                .map(|v| v.edges.len())
+                .sum::<usize>(),
+            4
+        );
+
+        // C -> A closes the cycle A -> B -> C -> A
+        g.vertices.get_mut(2).unwrap().push(0, 12);
+        g.vertices.get_mut(0).unwrap().edges.retain(|e| e.to != 2);
+        let result = get_candidates(&algo, &[g]);
+        assert_eq!(result[0][0].len(), 0);
+    }
 }
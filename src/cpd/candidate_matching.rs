@@ -1,14 +1,17 @@
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+mod buckets;
+mod classes;
+mod min_hash;
+mod union_find;
 
-use super::{
-    candidate_generation::Candidate,
-    graph_matching::{AlgoGraphMatching, MatchingResult},
-};
+use std::collections::HashSet;
+
+use super::graph_matching::{AlgoGraphMatching, MatchingResult};
 use crate::data::graph::Graph;
+use buckets::CandidateBuckets;
+pub use classes::CandidateClasses;
+use classes::ClassSupports;
 use dashmap::DashMap;
+use min_hash::MinHashIndex;
 use rayon::prelude::*;
 
 #[derive(Debug, Clone)]
@@ -23,6 +26,8 @@ use rayon::prelude::*;
 /// - `frequency_exact`: Number of graphs in which the pattern appears as an **exact match**.
 /// - `frequency_relaxed`: Number of graphs in which the pattern appears as either an
 ///   **exact match** or a **relaxed match**.
+/// - `level`: Taxonomy level of the vertex labels; 0 for the original labels, higher levels for
+///   patterns that only reach the support with generalized labels.
 ///
 /// # Notes
 /// - The `pattern.id` value is reassigned after matching to ensure that resulting patterns
@@ -32,6 +37,24 @@ pub struct PatternResult {
     pub pattern: Graph,
     pub frequency_exact: usize,
     pub frequency_relaxed: usize,
+    pub level: usize,
+}
+
+impl PatternResult {
+    /// Text representation of the pattern graph with its frequencies. Generalized patterns get
+    /// their taxonomy level appended to the graph line, e.g. `t # 3 * 2 / 4 @ 1`.
+    pub fn to_str_repr(&self) -> String {
+        let repr = self
+            .pattern
+            .to_str_repr(Some(self.frequency_exact), Some(self.frequency_relaxed));
+        if self.level == 0 {
+            return repr;
+        }
+        match repr.split_once('\n') {
+            Some((graph_line, rest)) => format!("{graph_line} @ {}\n{rest}", self.level),
+            None => format!("{repr} @ {}", self.level),
+        }
+    }
 }
 
 /// Specifies the strategy used to perform pairwise matching between candidate subgraphs.
@@ -42,30 +65,48 @@ pub struct PatternResult {
 ///
 /// # Variants
 ///
+/// All strategies compare the representatives of the exact match classes (`CandidateClasses`)
+/// and count every match as often as the other class has candidates. They assume that exact and
+/// relaxed matches do not depend on the chosen representative, so the supports are approximations
+/// for `GEDFastHungarian` and a time-limited `MaximumCommonSubgraph` (see
+/// `CandidateClasses::match_classes`).
+///
 /// - `Naive`:
 ///   Performs matching sequentially. Suitable for small datasets; easier to debug.
 /// - `Parallel`:
-///   Uses Rayon for parallel iteration and DashMap for a shared symmetric match cache.
+///   Uses Rayon for parallel iteration; every pair of classes is matched at most once.
 ///   Recommended for large candidate sets or many input graphs.
+/// - `MinHashLsh { bands, rows }`:
+///   Like `Parallel`, but each class is only compared with the classes that share a
+///   bucket of a MinHash locality-sensitive hashing index over the vertex and edge vectors,
+///   instead of all candidates. Candidates with the Jaccard similarity `s` of their vertex and
+///   edge multisets are compared with the probability `1 - (1 - s^rows)^bands`; more `bands`
+///   and fewer `rows` increase the recall of relaxed matches, but also the number of
+///   comparisons. Exact matches are always found.
+///
+/// Both `Naive` and `Parallel` group the classes by their isomorphism invariants first
+/// (`GraphInvariants`): matches are only searched between groups that
+/// `AlgoGraphMatching::invariants_within_threshold` accepts.
 ///
 /// The matching logic itself is provided by `AlgoGraphMatching`.
 #[derive(Debug)]
 pub enum AlgoCandidateMatching {
     Naive,
     Parallel,
+    MinHashLsh { bands: usize, rows: usize },
 }
 
 impl AlgoCandidateMatching {
     /// Executes the matching process for all candidate subgraphs across all input graphs.
     ///
-    /// Each candidate from graph *A* is compared to candidates from every *other* graph using
-    /// the selected `AlgoGraphMatching` strategy. If a candidate satisfies either the exact
-    /// or relaxed support threshold, it is included in the final result as a `PatternResult`.
+    /// The representative of each exact match class is compared to the representatives of the
+    /// other classes using the selected `AlgoGraphMatching` strategy. If a class satisfies either
+    /// the exact or relaxed support threshold, its representative is included in the final
+    /// result as a `PatternResult`.
     ///
     /// # Arguments
     ///
-    /// * `candidates` — A slice where each element contains all candidate subgraphs generated
-    ///   from one input graph.
+    /// * `classes` — The candidates of all input graphs, grouped into exact match classes.
     /// * `algo_graph_matching` — The matching algorithm used to compare two graphs.
     /// * `support_exact` — Minimum number of exact matches required for a candidate to be kept.
     /// * `support_relaxed` — Minimum number of relaxed-or-exact matches required.
@@ -82,8 +123,9 @@ impl AlgoCandidateMatching {
     /// runs exact/relaxed matching between their candidates:
     ///
     /// ```rust
-    /// use crate::candidate_matching::{AlgoCandidateMatching, PatternResult};
-    /// use crate::graph_matching::{AlgoGraphMatching, MatchingResult};
+    /// use crate::candidate_generation::Candidate;
+    /// use crate::candidate_matching::{AlgoCandidateMatching, CandidateClasses, PatternResult};
+    /// use crate::graph_matching::{AlgoGraphMatching, MatchingResult, SimilarityMeasure};
     /// use crate::data::graph::Graph;
     ///
     /// // Build two small graphs with identical structure
@@ -103,24 +145,32 @@ impl AlgoCandidateMatching {
     /// let g1 = make_graph(1);
     /// let g2 = make_graph(2);
     ///
-    /// // Each graph contributes its own set of candidates
+    /// // Each graph contributes its own set of candidates as (size index, candidate)
     /// // Here we pretend each graph itself is a single candidate
-    /// let candidates = vec![
-    ///     vec![g1.clone()],
-    ///     vec![g2.clone()],
-    /// ];
+    /// let candidate = |graph: Graph| Candidate {
+    ///     n_activity: 3,
+    ///     id_parent: graph.id,
+    ///     id_group: None,
+    ///     graph,
+    /// };
+    /// let candidates = vec![(0, candidate(g1)), (0, candidate(g2))];
     ///
     /// // Matching algorithm based on vertex/edge cosine similarity
     /// let matcher_algo = AlgoGraphMatching::CosineSimilarity {
+    ///     measure: SimilarityMeasure::Cosine,
     ///     alpha: 0.5,
     ///     matching_threshold: 0.8,
+    ///     idf: None,
+    ///     label_similarity: None,
     /// };
     ///
     /// // Use naive matching for simplicity
     /// let matcher = AlgoCandidateMatching::Naive;
     ///
+    /// let mut classes = CandidateClasses::new(false);
+    /// classes.extend(candidates, &matcher_algo);
     /// let patterns: Vec<PatternResult> =
-    ///     matcher.run_matching(&candidates, &matcher_algo, 1, 1);
+    ///     matcher.run_matching(&classes, &matcher_algo, 1, 1);
     ///
     /// assert_eq!(patterns.len(), 1);
     /// assert_eq!(patterns[0].frequency_exact, 2);  // g1 matches g2 exactly
@@ -135,30 +185,30 @@ impl AlgoCandidateMatching {
     /// # Notes
     ///
     /// - The method performs candidate-to-candidate comparisons across **different** input graphs only.
-    /// - In parallel mode, a shared symmetric cache ensures each pair of graphs is matched at most once.
+    /// - In naive and parallel mode, each pair of exact match classes is matched at most once.
+    /// - The patterns are the representatives of the classes, in the order of the classes.
     /// - After matching, pattern IDs are reassigned to ensure stable ordering in output.
     pub fn run_matching(
         &self,
-        candidates: &[Vec<Vec<Candidate>>],
+        classes: &CandidateClasses,
         algo_graph_matching: &AlgoGraphMatching,
         support_exact: usize,
         support_relaxed: usize,
-        compare_only_same_size: bool,
     ) -> Vec<PatternResult> {
         let mut result = match self {
-            AlgoCandidateMatching::Naive => run_naive(
-                candidates,
-                algo_graph_matching,
-                support_exact,
-                support_relaxed,
-                compare_only_same_size,
-            ),
-            AlgoCandidateMatching::Parallel => run_parallel(
-                candidates,
+            AlgoCandidateMatching::Naive => {
+                run_naive(classes, algo_graph_matching, support_exact, support_relaxed)
+            }
+            AlgoCandidateMatching::Parallel => {
+                run_parallel(classes, algo_graph_matching, support_exact, support_relaxed)
+            }
+            AlgoCandidateMatching::MinHashLsh { bands, rows } => run_min_hash_lsh(
+                classes,
                 algo_graph_matching,
                 support_exact,
                 support_relaxed,
-                compare_only_same_size,
+                *bands,
+                *rows,
             ),
         };
         // Update ids of graphs
@@ -170,167 +220,151 @@ impl AlgoCandidateMatching {
 }
 
 fn run_naive(
-    candidates: &[Vec<Vec<Candidate>>],
+    classes: &CandidateClasses,
     algo_graph_matching: &AlgoGraphMatching,
     support_exact: usize,
     support_relaxed: usize,
-    compare_only_same_size: bool,
 ) -> Vec<PatternResult> {
+    let buckets = CandidateBuckets::new(classes.invariants(), algo_graph_matching);
+    let supports = ClassSupports::new(classes);
     let mut resulting_candidates = Vec::new();
     let mut can_be_skipped: HashSet<usize> = HashSet::new();
-    let mut match_results: HashMap<(usize, usize), MatchingResult> = HashMap::new();
     let mut matches: Vec<usize> = Vec::new();
-    for candidates_of_graph_a in candidates.iter() {
-        for (i_n_a, candidate_n_a) in candidates_of_graph_a.iter().enumerate() {
-            for candidate_a in candidate_n_a.iter() {
-                if can_be_skipped.contains(&candidate_a.graph.id) {
-                    continue;
-                }
-                let mut freq_relaxed = 0;
-                matches.clear();
-                for candidates_of_graph_b in candidates.iter() {
-                    // Only compare graphs of the same n size :)
-                    let candidates_of_graph_b: Box<dyn Iterator<Item = &Candidate>> =
-                        if compare_only_same_size {
-                            Box::new(candidates_of_graph_b[i_n_a].iter())
-                        } else {
-                            Box::new(candidates_of_graph_b.iter().flatten())
-                        };
-
-                    for candidate_b in candidates_of_graph_b {
-                        let key = if candidate_a.graph.id < candidate_b.graph.id {
-                            (candidate_a.graph.id, candidate_b.graph.id)
-                        } else {
-                            (candidate_b.graph.id, candidate_a.graph.id)
-                        };
-                        let match_result = match_results.entry(key).or_insert_with(|| {
-                            algo_graph_matching.match_graphs(&candidate_a.graph, &candidate_b.graph)
-                        });
-                        match match_result {
-                            MatchingResult::ExactMatch => {
-                                matches.push(candidate_b.graph.id);
-                                freq_relaxed += 1;
-                            }
-                            MatchingResult::RelaxedMatch => freq_relaxed += 1,
-                            MatchingResult::NoMatch => {
-                                // Nothing
-                            }
-                        }
-                    }
-                }
-                let freq_exact = matches.len();
-                if freq_exact >= support_exact || freq_relaxed >= support_relaxed {
-                    resulting_candidates.push(PatternResult {
-                        pattern: candidate_a.graph.clone(),
-                        frequency_exact: freq_exact,
-                        frequency_relaxed: freq_relaxed,
-                    });
-                }
-                can_be_skipped.extend(&matches);
+    for class_a in 0..classes.number_of_classes() {
+        // The matches with the earlier classes are already counted
+        matches.clear();
+        for class_b in buckets.may_match_all(class_a) {
+            if class_b <= class_a || !classes.comparable(class_a, class_b) {
+                continue;
+            }
+            let result = classes.match_classes(class_a, class_b, algo_graph_matching);
+            supports.add(class_a, class_b, result);
+            supports.add(class_b, class_a, result);
+            if result == MatchingResult::ExactMatch {
+                matches.push(class_b);
             }
         }
+        if can_be_skipped.contains(&class_a) {
+            continue;
+        }
+        let freq_exact = supports.exact(class_a);
+        let freq_relaxed = supports.relaxed(class_a);
+        if freq_exact >= support_exact || freq_relaxed >= support_relaxed {
+            resulting_candidates.push(PatternResult {
+                pattern: classes.representative(class_a).graph.clone(),
+                frequency_exact: freq_exact,
+                frequency_relaxed: freq_relaxed,
+                level: 0,
+            });
+        }
+        can_be_skipped.extend(&matches);
     }
     resulting_candidates
 }
 
 fn run_parallel(
-    candidates: &[Vec<Vec<Candidate>>],
+    classes: &CandidateClasses,
     algo_graph_matching: &AlgoGraphMatching,
     support_exact: usize,
     support_relaxed: usize,
-    compare_only_same_size: bool,
 ) -> Vec<PatternResult> {
-    // Symmetric match result cache
-    let match_cache = Arc::new(DashMap::<(usize, usize), MatchingResult>::new());
-
-    // Parallel map over all groups
-    let all_results: Vec<PatternResult> = candidates
-        .par_iter()
-        .flat_map(|candidates_of_graph_a| {
-            let mut local = Vec::with_capacity(candidates_of_graph_a.len() / 4);
-
-            for (i_n_a, candidate_n_a) in candidates_of_graph_a.iter().enumerate() {
-                for candidate_a in candidate_n_a.iter() {
-                    let mut freq_relaxed = 0;
-                    let mut freq_exact = 0;
+    let buckets = CandidateBuckets::new(classes.invariants(), algo_graph_matching);
+    let supports = ClassSupports::new(classes);
+    // Matches between the representatives of compatible buckets, every pair only once
+    (0..classes.number_of_classes())
+        .into_par_iter()
+        .for_each(|class_a| {
+            for class_b in buckets.may_match_all(class_a) {
+                if class_b <= class_a || !classes.comparable(class_a, class_b) {
+                    continue;
+                }
+                let result = classes.match_classes(class_a, class_b, algo_graph_matching);
+                supports.add(class_a, class_b, result);
+                supports.add(class_b, class_a, result);
+            }
+        });
 
-                    // Check all other groups
-                    for candidates_of_graph_b in candidates.iter() {
-                        // Only compare graphs of the same n size :)
-                        let candidates_of_graph_b: Box<dyn Iterator<Item = &Candidate>> =
-                            if compare_only_same_size {
-                                Box::new(candidates_of_graph_b[i_n_a].iter())
-                            } else {
-                                Box::new(candidates_of_graph_b.iter().flatten())
-                            };
+    // One pattern per exact match class
+    (0..classes.number_of_classes())
+        .filter_map(|class| {
+            let freq_exact = supports.exact(class);
+            let freq_relaxed = supports.relaxed(class);
+            if freq_exact >= support_exact || freq_relaxed >= support_relaxed {
+                Some(PatternResult {
+                    pattern: classes.representative(class).graph.clone(),
+                    frequency_exact: freq_exact,
+                    frequency_relaxed: freq_relaxed,
+                    level: 0,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
 
-                        for candidate_b in candidates_of_graph_b {
-                            let (a, b) = if candidate_a.graph.id < candidate_b.graph.id {
-                                (candidate_a.graph.id, candidate_b.graph.id)
-                            } else {
-                                (candidate_b.graph.id, candidate_a.graph.id)
-                            };
+/// Maximum number of match results that `run_min_hash_lsh` caches
+const MAX_CACHED_PAIRS: usize = 1 << 20;
 
-                            let result = *match_cache.entry((a, b)).or_insert_with(|| {
-                                algo_graph_matching
-                                    .match_graphs(&candidate_a.graph, &candidate_b.graph)
-                            });
+fn run_min_hash_lsh(
+    classes: &CandidateClasses,
+    algo_graph_matching: &AlgoGraphMatching,
+    support_exact: usize,
+    support_relaxed: usize,
+    bands: usize,
+    rows: usize,
+) -> Vec<PatternResult> {
+    let graphs: Vec<&Graph> = (0..classes.number_of_classes())
+        .map(|class| &classes.representative(class).graph)
+        .collect();
+    let index = MinHashIndex::new(&graphs, bands, rows);
+    let buckets = CandidateBuckets::new(classes.invariants(), algo_graph_matching);
 
-                            match result {
-                                MatchingResult::ExactMatch => {
-                                    freq_relaxed += 1;
-                                    freq_exact += 1;
-                                }
-                                MatchingResult::RelaxedMatch => {
-                                    freq_relaxed += 1;
-                                }
-                                MatchingResult::NoMatch => {
-                                    // Nothing
-                                }
-                            }
+    // Symmetric match result cache, bounded
+    let match_cache = DashMap::<(usize, usize), MatchingResult>::new();
+    (0..classes.number_of_classes())
+        .into_par_iter()
+        .filter_map(|class_a| {
+            let mut matches = Vec::new();
+            let mut relaxed_matches = Vec::new();
+            for class_b in index.similar(class_a) {
+                if !classes.comparable(class_a, class_b) || !buckets.may_match(class_a, class_b) {
+                    continue;
+                }
+                let key = (class_a.min(class_b), class_a.max(class_b));
+                let result = match match_cache.get(&key) {
+                    Some(result) => *result,
+                    None => {
+                        let result = classes.match_classes(class_a, class_b, algo_graph_matching);
+                        if match_cache.len() < MAX_CACHED_PAIRS {
+                            match_cache.insert(key, result);
                         }
+                        result
+                    }
+                };
+                match result {
+                    MatchingResult::ExactMatch => {
+                        matches.push(class_b);
+                        relaxed_matches.push(class_b);
                     }
-                    if freq_exact >= support_exact || freq_relaxed >= support_relaxed {
-                        local.push(PatternResult {
-                            pattern: candidate_a.graph.clone(),
-                            frequency_exact: freq_exact,
-                            frequency_relaxed: freq_relaxed,
-                        });
+                    MatchingResult::RelaxedMatch => relaxed_matches.push(class_b),
+                    MatchingResult::NoMatch => {
+                        // Nothing
                     }
                 }
             }
-
-            local
-        })
-        .collect();
-    let mut unique = Vec::with_capacity(all_results.len());
-    let mut visited = HashSet::<usize>::new();
-
-    for (i_one_graph, one_graph) in all_results.iter().enumerate() {
-        if visited.contains(&one_graph.pattern.id) {
-            continue;
-        }
-        visited.insert(one_graph.pattern.id);
-
-        for i_other_graph in (i_one_graph + 1)..all_results.len() {
-            let other_graph = all_results.get(i_other_graph).unwrap();
-            if one_graph.pattern.id == other_graph.pattern.id {
-                continue;
-            }
-
-            let key = if one_graph.pattern.id < other_graph.pattern.id {
-                (one_graph.pattern.id, other_graph.pattern.id)
+            let freq_exact = classes.support(&matches);
+            let freq_relaxed = classes.support(&relaxed_matches);
+            if freq_exact >= support_exact || freq_relaxed >= support_relaxed {
+                Some(PatternResult {
+                    pattern: classes.representative(class_a).graph.clone(),
+                    frequency_exact: freq_exact,
+                    frequency_relaxed: freq_relaxed,
+                    level: 0,
+                })
             } else {
-                (other_graph.pattern.id, one_graph.pattern.id)
-            };
-
-            if let Some(result) = match_cache.get(&key)
-                && *result == MatchingResult::ExactMatch
-            {
-                visited.insert(other_graph.pattern.id);
+                None
             }
-        }
-        unique.push(one_graph.clone());
-    }
-    unique
+        })
+        .collect()
 }
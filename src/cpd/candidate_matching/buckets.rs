@@ -0,0 +1,116 @@
+use crate::{cpd::graph_matching::AlgoGraphMatching, data::graph::GraphInvariants};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Exact match classes grouped by their isomorphism invariants (`GraphInvariants`).
+///
+/// Matches can only occur between buckets whose invariants are within the matching threshold of
+/// the graph matcher (`AlgoGraphMatching::invariants_within_threshold`), so all other pairs are
+/// skipped.
+pub(super) struct CandidateBuckets {
+    /// Bucket of every class
+    bucket_of: Vec<usize>,
+    /// Classes of every bucket in ascending order
+    members: Vec<Vec<usize>>,
+    /// Buckets that may match every bucket (including itself) in ascending order
+    compatible: Vec<Vec<usize>>,
+}
+
+impl CandidateBuckets {
+    pub(super) fn new(
+        invariants: &[GraphInvariants],
+        algo_graph_matching: &AlgoGraphMatching,
+    ) -> Self {
+        let mut bucket_by_invariants: HashMap<&GraphInvariants, usize> = HashMap::new();
+        let mut bucket_invariants: Vec<&GraphInvariants> = Vec::new();
+        let mut members: Vec<Vec<usize>> = Vec::new();
+        let mut bucket_of = Vec::with_capacity(invariants.len());
+        for (class, class_invariants) in invariants.iter().enumerate() {
+            let bucket = *bucket_by_invariants
+                .entry(class_invariants)
+                .or_insert_with(|| {
+                    bucket_invariants.push(class_invariants);
+                    members.push(Vec::new());
+                    members.len() - 1
+                });
+            members[bucket].push(class);
+            bucket_of.push(bucket);
+        }
+        let compatible = bucket_invariants
+            .par_iter()
+            .map(|one| {
+                bucket_invariants
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, other)| {
+                        one == *other || algo_graph_matching.invariants_within_threshold(one, other)
+                    })
+                    .map(|(bucket, _)| bucket)
+                    .collect()
+            })
+            .collect();
+        CandidateBuckets {
+            bucket_of,
+            members,
+            compatible,
+        }
+    }
+
+    /// Whether the representatives of two classes may be an exact or relaxed match
+    pub(super) fn may_match(&self, one_class: usize, other_class: usize) -> bool {
+        self.compatible[self.bucket_of[one_class]]
+            .binary_search(&self.bucket_of[other_class])
+            .is_ok()
+    }
+
+    /// Classes whose representatives may be an exact or relaxed match of the representative of
+    /// the class, including itself, bucket by bucket
+    pub(super) fn may_match_all(&self, class: usize) -> impl Iterator<Item = usize> + '_ {
+        self.compatible[self.bucket_of[class]]
+            .iter()
+            .flat_map(|bucket| self.members[*bucket].iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpd::test_utils::make_path_graph;
+
+    #[test]
+    fn test_candidate_buckets() {
+        let graphs = [
+            make_path_graph(0, &[1, 2]),
+            make_path_graph(1, &[1, 2, 3]),
+            make_path_graph(2, &[1, 2]),
+            make_path_graph(3, &[1, 3]),
+            make_path_graph(4, &[1, 2, 3, 4, 5, 6]),
+        ];
+        let invariants: Vec<GraphInvariants> = graphs
+            .iter()
+            .map(|graph| graph.get_invariants().clone())
+            .collect();
+
+        let buckets = CandidateBuckets::new(&invariants, &AlgoGraphMatching::VF2IsomorphismTest);
+        assert!(buckets.may_match(2, 0));
+        assert!(!buckets.may_match(0, 3));
+        assert_eq!(buckets.may_match_all(2).collect::<Vec<_>>(), vec![0, 2]);
+
+        // One vertex and one edge more are within an absolute GED of 2
+        let buckets = CandidateBuckets::new(
+            &invariants,
+            &AlgoGraphMatching::GEDFastHungarian {
+                edit_costs: Default::default(),
+                normalization: Default::default(),
+                matching_threshold: 2.0,
+            },
+        );
+        assert!(buckets.may_match(0, 3));
+        assert!(buckets.may_match(0, 1));
+        assert!(!buckets.may_match(0, 4));
+        assert_eq!(
+            buckets.may_match_all(0).collect::<Vec<_>>(),
+            vec![0, 2, 1, 3]
+        );
+    }
+}
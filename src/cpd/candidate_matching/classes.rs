@@ -0,0 +1,539 @@
+use super::union_find::ConcurrentUnionFind;
+use crate::{
+    cpd::{
+        candidate_generation::Candidate,
+        graph_matching::{AlgoGraphMatching, MatchingResult},
+    },
+    data::{
+        graph::{Graph, GraphInvariants},
+        taxonomy::Taxonomy,
+    },
+};
+use dashmap::DashSet;
+use rayon::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Candidates grouped into exact match classes while they are generated.
+///
+/// Only the first candidate of every class (its representative) is kept as a graph; every other
+/// candidate just increases the size of its class. Exact matching is assumed to be transitive,
+/// so every candidate is only compared with the representatives that have the same invariants
+/// (and the same candidate size, if only candidates of the same size are compared).
+///
+/// The edge-subset variants of one induced candidate form a group (`Candidate::id_group`) that
+/// counts as one candidate, even if its variants fall into several classes.
+#[derive(Debug, Default)]
+pub struct CandidateClasses {
+    compare_only_same_size: bool,
+    /// First candidate of every class
+    representatives: Vec<Candidate>,
+    /// Index of the candidate size of every class
+    size_indices: Vec<usize>,
+    /// Number of candidates without a group of variants of every class
+    sizes: Vec<usize>,
+    /// Groups of variants of the candidates of every class, sorted
+    groups: Vec<Vec<usize>>,
+    /// Number of candidates of all classes, every group of variants counted once
+    number_of_candidates: usize,
+    /// Invariants of every class
+    invariants: Vec<GraphInvariants>,
+    /// Classes per (Index of the candidate size if only the same sizes are compared, Invariants)
+    buckets: HashMap<(Option<usize>, GraphInvariants), Vec<usize>>,
+}
+
+impl CandidateClasses {
+    pub fn new(compare_only_same_size: bool) -> Self {
+        CandidateClasses {
+            compare_only_same_size,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a batch of (size index, candidate), in order
+    pub fn extend(
+        &mut self,
+        candidates: Vec<(usize, Candidate)>,
+        algo_graph_matching: &AlgoGraphMatching,
+    ) {
+        let entries: Vec<_> = candidates
+            .into_iter()
+            .map(|(i_n, c)| match c.id_group {
+                Some(id_group) => (i_n, c, 0, vec![id_group]),
+                None => (i_n, c, 1, Vec::new()),
+            })
+            .collect();
+        // A group of variants is counted with its induced candidate
+        self.number_of_candidates += entries
+            .iter()
+            .filter(|(_, candidate, _, _)| {
+                candidate
+                    .id_group
+                    .is_none_or(|id_group| id_group == candidate.graph.id)
+            })
+            .count();
+        self.insert(entries, algo_graph_matching);
+    }
+
+    /// Classes of the candidates with the labels generalized to the `level` of the taxonomy.
+    /// Classes whose generalizations are exact matches are merged.
+    pub fn generalized(
+        &self,
+        taxonomy: &Taxonomy,
+        level: usize,
+        algo_graph_matching: &AlgoGraphMatching,
+    ) -> CandidateClasses {
+        let entries = (0..self.number_of_classes())
+            .into_par_iter()
+            .map(|class| {
+                let candidate = &self.representatives[class];
+                let generalized = Candidate {
+                    n_activity: candidate.n_activity,
+                    id_parent: candidate.id_parent,
+                    id_group: candidate.id_group,
+                    graph: taxonomy.generalize(&candidate.graph, level),
+                };
+                (
+                    self.size_indices[class],
+                    generalized,
+                    self.sizes[class],
+                    self.groups[class].clone(),
+                )
+            })
+            .collect();
+        let mut classes = CandidateClasses::new(self.compare_only_same_size);
+        classes.number_of_candidates = self.number_of_candidates;
+        classes.insert(entries, algo_graph_matching);
+        classes
+    }
+
+    /// Adds the (Size index, Candidate, Number of candidates without a group, Groups) entries,
+    /// in order.
+    ///
+    /// Every entry is first compared with the representatives of its bucket of invariants, in
+    /// parallel. The remaining entries of the same bucket are then merged into exact match
+    /// classes with a `ConcurrentUnionFind`; an entry is only compared while it is still the
+    /// root of its set, so every class costs one comparison per other class of the bucket. The
+    /// root of a set is its first entry, so new classes are numbered in the order of the
+    /// entries. Only exact matches are recorded: the relaxed matches are left to the candidate
+    /// matching, which runs after the graph matcher has been prepared for all classes.
+    fn insert(
+        &mut self,
+        entries: Vec<(usize, Candidate, usize, Vec<usize>)>,
+        algo_graph_matching: &AlgoGraphMatching,
+    ) {
+        let is_exact_match = |one_graph: &Graph, other_graph: &Graph| {
+            algo_graph_matching.match_graphs_uncounted(one_graph, other_graph)
+                == MatchingResult::ExactMatch
+        };
+        let keys: Vec<(Option<usize>, GraphInvariants)> = entries
+            .par_iter()
+            .map(|(i_n, candidate, _, _)| {
+                (
+                    self.compare_only_same_size.then_some(*i_n),
+                    candidate.graph.get_invariants().clone(),
+                )
+            })
+            .collect();
+
+        // 1. Existing class that every entry is an exact match of, if any
+        let existing_classes: Vec<Option<usize>> = entries
+            .par_iter()
+            .zip(keys.par_iter())
+            .map(|((_, candidate, _, _), key)| {
+                self.buckets.get(key)?.iter().copied().find(|class| {
+                    is_exact_match(&self.representatives[*class].graph, &candidate.graph)
+                })
+            })
+            .collect();
+
+        // 2. Exact match classes of the other entries per bucket
+        let mut new_entries_by_key: HashMap<&(Option<usize>, GraphInvariants), Vec<usize>> =
+            HashMap::new();
+        for (i_entry, key) in keys.iter().enumerate() {
+            if existing_classes[i_entry].is_none() {
+                new_entries_by_key.entry(key).or_default().push(i_entry);
+            }
+        }
+        let new_classes = ConcurrentUnionFind::new(entries.len());
+        new_entries_by_key.par_iter().for_each(|(_, new_entries)| {
+            new_entries
+                .par_iter()
+                .enumerate()
+                .for_each(|(position, i_entry)| {
+                    for i_other in new_entries[position + 1..].iter() {
+                        if new_classes.find(*i_entry) != *i_entry {
+                            // An earlier entry compares the others
+                            return;
+                        }
+                        if new_classes.find(*i_other) == *i_other
+                            && is_exact_match(
+                                &entries[*i_entry].1.graph,
+                                &entries[*i_other].1.graph,
+                            )
+                        {
+                            new_classes.union(*i_entry, *i_other);
+                        }
+                    }
+                });
+        });
+
+        // 3. Classes in the order of the entries
+        let mut class_of_entry = vec![usize::MAX; entries.len()];
+        for (i_entry, (((i_n, candidate, size, groups), key), existing_class)) in entries
+            .into_iter()
+            .zip(keys)
+            .zip(existing_classes)
+            .enumerate()
+        {
+            let root = new_classes.find(i_entry);
+            let class = match existing_class {
+                Some(class) => class,
+                None if root != i_entry => class_of_entry[root],
+                None => {
+                    let class = self.representatives.len();
+                    self.representatives.push(candidate);
+                    self.size_indices.push(i_n);
+                    self.sizes.push(0);
+                    self.groups.push(Vec::new());
+                    self.invariants.push(key.1.clone());
+                    self.buckets.entry(key).or_default().push(class);
+                    class
+                }
+            };
+            self.sizes[class] += size;
+            for group in groups {
+                if let Err(position) = self.groups[class].binary_search(&group) {
+                    self.groups[class].insert(position, group);
+                }
+            }
+            class_of_entry[i_entry] = class;
+        }
+    }
+
+    /// Number of classes
+    pub fn number_of_classes(&self) -> usize {
+        self.representatives.len()
+    }
+
+    /// Number of candidates of all classes
+    pub fn number_of_candidates(&self) -> usize {
+        self.number_of_candidates
+    }
+
+    /// First candidate of the class
+    pub fn representative(&self, class: usize) -> &Candidate {
+        &self.representatives[class]
+    }
+
+    /// Number of candidates of the class
+    pub fn size(&self, class: usize) -> usize {
+        self.sizes[class] + self.groups[class].len()
+    }
+
+    /// Number of candidates of the classes, every group of variants counted once
+    pub fn support(&self, classes: &[usize]) -> usize {
+        let groups: HashSet<usize> = classes
+            .iter()
+            .flat_map(|class| self.groups[*class].iter().copied())
+            .collect();
+        classes
+            .iter()
+            .map(|class| self.sizes[*class])
+            .sum::<usize>()
+            + groups.len()
+    }
+
+    /// Invariants of all classes
+    pub fn invariants(&self) -> &[GraphInvariants] {
+        &self.invariants
+    }
+
+    /// Whether the candidates of two classes are compared at all
+    pub fn comparable(&self, one_class: usize, other_class: usize) -> bool {
+        !self.compare_only_same_size
+            || self.size_indices[one_class] == self.size_indices[other_class]
+    }
+
+    /// Match result of the representatives of two classes; the candidates of a class are exact
+    /// matches of each other.
+    ///
+    /// The result stands for all pairs of candidates of both classes. This is exact for matchers
+    /// that give the same result for isomorphic graphs. It is an approximation for
+    /// `GEDFastHungarian`, whose node assignment depends on the vertex order, and for
+    /// `MaximumCommonSubgraph` once its time limit is reached: another candidate of a class may
+    /// be matched differently than its representative.
+    pub fn match_classes(
+        &self,
+        one_class: usize,
+        other_class: usize,
+        algo_graph_matching: &AlgoGraphMatching,
+    ) -> MatchingResult {
+        if one_class == other_class {
+            return MatchingResult::ExactMatch;
+        }
+        let (one_class, other_class) = (one_class.min(other_class), one_class.max(other_class));
+        algo_graph_matching.match_graphs(
+            &self.representatives[one_class].graph,
+            &self.representatives[other_class].graph,
+        )
+    }
+}
+
+/// Exact and relaxed support of every class, counted while the pairs of classes are matched.
+///
+/// Every class is an exact match of itself. A group of variants is counted once per class, the
+/// first time a class with a candidate of the group matches; only then a lock is taken.
+pub(super) struct ClassSupports<'a> {
+    classes: &'a CandidateClasses,
+    exact: Vec<AtomicUsize>,
+    relaxed: Vec<AtomicUsize>,
+    /// Counted (Class, Group) pairs of the exact support
+    exact_groups: DashSet<(usize, usize)>,
+    /// Counted (Class, Group) pairs of the relaxed support
+    relaxed_groups: DashSet<(usize, usize)>,
+}
+
+impl<'a> ClassSupports<'a> {
+    pub(super) fn new(classes: &'a CandidateClasses) -> Self {
+        let supports = ClassSupports {
+            classes,
+            exact: (0..classes.number_of_classes())
+                .map(|class| AtomicUsize::new(classes.size(class)))
+                .collect(),
+            relaxed: (0..classes.number_of_classes())
+                .map(|class| AtomicUsize::new(classes.size(class)))
+                .collect(),
+            exact_groups: DashSet::new(),
+            relaxed_groups: DashSet::new(),
+        };
+        for (class, groups) in classes.groups.iter().enumerate() {
+            for group in groups {
+                supports.exact_groups.insert((class, *group));
+                supports.relaxed_groups.insert((class, *group));
+            }
+        }
+        supports
+    }
+
+    /// Counts the candidates of `other_class` for `class` according to their match result
+    pub(super) fn add(&self, class: usize, other_class: usize, result: MatchingResult) {
+        let count = |support: &AtomicUsize, counted_groups: &DashSet<(usize, usize)>| {
+            let new_groups = self.classes.groups[other_class]
+                .iter()
+                .filter(|group| counted_groups.insert((class, **group)))
+                .count();
+            support.fetch_add(
+                self.classes.sizes[other_class] + new_groups,
+                Ordering::Relaxed,
+            );
+        };
+        match result {
+            MatchingResult::ExactMatch => {
+                count(&self.exact[class], &self.exact_groups);
+                count(&self.relaxed[class], &self.relaxed_groups);
+            }
+            MatchingResult::RelaxedMatch => count(&self.relaxed[class], &self.relaxed_groups),
+            MatchingResult::NoMatch => {
+                // Nothing
+            }
+        }
+    }
+
+    /// Number of candidates of the exact matches of the class
+    pub(super) fn exact(&self, class: usize) -> usize {
+        self.exact[class].load(Ordering::Relaxed)
+    }
+
+    /// Number of candidates of the exact and relaxed matches of the class
+    pub(super) fn relaxed(&self, class: usize) -> usize {
+        self.relaxed[class].load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpd::{
+        candidate_matching::AlgoCandidateMatching,
+        graph_matching::GEDEditCosts,
+        test_utils::{make_candidate, make_graph},
+    };
+
+    #[test]
+    fn test_candidate_classes() {
+        let algo = AlgoGraphMatching::VF2IsomorphismTest;
+        let mut classes = CandidateClasses::new(false);
+        classes.extend(
+            vec![
+                (0, make_candidate(&[1, 2])),
+                (0, make_candidate(&[1, 3])),
+                (0, make_candidate(&[1, 2])),
+            ],
+            &algo,
+        );
+        classes.extend(
+            vec![
+                (0, make_candidate(&[1, 3])),
+                (1, make_candidate(&[1, 2, 3])),
+            ],
+            &algo,
+        );
+        assert_eq!(classes.number_of_classes(), 3);
+        assert_eq!(classes.number_of_candidates(), 5);
+        assert_eq!(classes.size(0), 2);
+        assert_eq!(classes.size(1), 2);
+        assert_eq!(classes.representative(2).n_activity, 3);
+        assert_eq!(
+            classes.match_classes(1, 1, &algo),
+            MatchingResult::ExactMatch
+        );
+        assert_eq!(classes.match_classes(1, 0, &algo), MatchingResult::NoMatch);
+        assert!(classes.comparable(0, 2));
+
+        // Labels 2 and 3 roll up to 10
+        let mut taxonomy_file = std::env::temp_dir();
+        taxonomy_file.push("cpd_test_candidate_classes_taxonomy.txt");
+        std::fs::write(&taxonomy_file, "p 1 2 10\np 1 3 10\n").unwrap();
+        let taxonomy = Taxonomy::from_file(&taxonomy_file).unwrap();
+        std::fs::remove_file(&taxonomy_file).unwrap();
+        let generalized = classes.generalized(&taxonomy, 1, &algo);
+        assert_eq!(generalized.number_of_classes(), 2);
+        assert_eq!(generalized.size(0), 4);
+        assert_eq!(generalized.representative(0).graph.vertices[1].label, 10);
+
+        // Only the candidates of the same size are compared
+        let mut classes = CandidateClasses::new(true);
+        classes.extend(
+            vec![(0, make_candidate(&[1, 2])), (1, make_candidate(&[1, 2]))],
+            &algo,
+        );
+        assert_eq!(classes.number_of_classes(), 2);
+        assert!(!classes.comparable(0, 1));
+    }
+
+    #[test]
+    fn test_variant_groups() {
+        // Two isomorphic variants of the induced candidate with id 7
+        let variant = |labels: &[usize], id: usize| {
+            let mut candidate = make_candidate(labels);
+            candidate.graph.id = id;
+            candidate.id_group = Some(7);
+            candidate
+        };
+        let algo = AlgoGraphMatching::VF2IsomorphismTest;
+        let mut classes = CandidateClasses::new(false);
+        classes.extend(
+            vec![
+                (0, variant(&[1, 2, 3], 7)),
+                (0, variant(&[1, 2], 8)),
+                (0, variant(&[1, 2], 9)),
+                (0, make_candidate(&[1, 2])),
+            ],
+            &algo,
+        );
+        assert_eq!(classes.number_of_classes(), 2);
+        assert_eq!(classes.number_of_candidates(), 2);
+        assert_eq!(classes.size(0), 1);
+        assert_eq!(classes.size(1), 2);
+        assert_eq!(classes.support(&[0, 1]), 2);
+
+        // The group only counts once, even if several matching classes contain it
+        let supports = ClassSupports::new(&classes);
+        supports.add(1, 0, MatchingResult::RelaxedMatch);
+        assert_eq!(supports.exact(1), 2);
+        assert_eq!(supports.relaxed(1), 2);
+        supports.add(0, 1, MatchingResult::ExactMatch);
+        assert_eq!(supports.exact(0), 2);
+
+        for matcher in [
+            AlgoCandidateMatching::Naive,
+            AlgoCandidateMatching::Parallel,
+        ] {
+            let patterns = matcher.run_matching(&classes, &algo, 2, 2);
+            assert_eq!(patterns.len(), 1);
+            assert_eq!(patterns[0].frequency_exact, 2);
+        }
+    }
+
+    #[test]
+    fn test_representative_approximation() {
+        // Two vertex orders of the same candidate and another candidate
+        let one_order = make_graph(
+            &[(2, 1), (2, 1), (2, 1)],
+            &[(0, 2, 0), (2, 0, 0), (2, 1, 0)],
+        );
+        let other_order = make_graph(
+            &[(2, 1), (2, 1), (2, 1)],
+            &[(0, 1, 0), (1, 0, 0), (1, 2, 0)],
+        );
+        let other = make_graph(
+            &[(2, 1), (1, 1), (1, 1), (2, 1)],
+            &[
+                (0, 2, 0),
+                (1, 0, 0),
+                (1, 2, 0),
+                (1, 3, 0),
+                (2, 0, 0),
+                (2, 1, 0),
+                (3, 0, 0),
+                (3, 1, 0),
+                (3, 2, 0),
+            ],
+        );
+        let candidates = [one_order, other_order, other];
+        let extend = |classes: &mut CandidateClasses, algo: &AlgoGraphMatching| {
+            classes.extend(
+                candidates
+                    .iter()
+                    .map(|graph| {
+                        let candidate = Candidate {
+                            n_activity: graph.vertices.len(),
+                            id_parent: 0,
+                            id_group: None,
+                            graph: graph.clone(),
+                        };
+                        (0, candidate)
+                    })
+                    .collect(),
+                algo,
+            )
+        };
+
+        // The maximum common subgraph does not depend on the vertex order within the time limit
+        let algo = AlgoGraphMatching::MaximumCommonSubgraph {
+            time_limit: std::time::Duration::from_secs(10),
+            matching_threshold: 0.5,
+        };
+        let mut classes = CandidateClasses::new(false);
+        extend(&mut classes, &algo);
+        assert_eq!(classes.number_of_classes(), 2);
+        for candidate in &candidates[..2] {
+            assert_eq!(
+                classes.match_classes(0, 1, &algo),
+                algo.match_graphs(candidate, &candidates[2])
+            );
+        }
+
+        // The GED approximation does: the class only gets the result of its representative
+        let algo = AlgoGraphMatching::GEDFastHungarian {
+            edit_costs: GEDEditCosts::default(),
+            normalization: Default::default(),
+            matching_threshold: 9.0,
+        };
+        let mut classes = CandidateClasses::new(false);
+        extend(&mut classes, &algo);
+        assert_eq!(classes.number_of_classes(), 2);
+        assert_eq!(classes.match_classes(0, 1, &algo), MatchingResult::NoMatch);
+        assert_eq!(
+            algo.match_graphs(&candidates[0], &candidates[2]),
+            MatchingResult::NoMatch
+        );
+        assert_eq!(
+            algo.match_graphs(&candidates[1], &candidates[2]),
+            MatchingResult::RelaxedMatch
+        );
+    }
+}
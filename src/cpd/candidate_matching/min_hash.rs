@@ -0,0 +1,126 @@
+use crate::data::graph::Graph;
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+/// Locality-sensitive hashing index of graphs: MinHash signatures over the shingles of the vertex
+/// and edge vectors, split into `bands` of `rows` values each.
+///
+/// Two graphs whose shingle sets have the Jaccard similarity `s` share a bucket in at least one
+/// band with the probability `1 - (1 - s^rows)^bands`. Graphs with equal vertex and edge vectors
+/// always share all buckets. Both `bands` and `rows` must be at least 1.
+pub(super) struct MinHashIndex {
+    /// Hash of the signature values of each band per graph
+    band_keys: Vec<Vec<u64>>,
+    /// Graphs per hash of the signature values, per band
+    buckets: Vec<HashMap<u64, Vec<usize>>>,
+}
+
+fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// SplitMix64 finalizer; a cheap permutation of the shingle hashes per hash function
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Shingles of a graph: every vertex and edge vector key, repeated by its count
+fn shingles(graph: &Graph) -> Vec<u64> {
+    let vertex_shingles = graph
+        .get_vertex_vector()
+        .iter()
+        .flat_map(|(key, count)| (0..*count).map(move |i| hash_of(&(0u8, key, i))))
+        .collect::<Vec<_>>();
+    let edge_shingles = graph
+        .get_edge_vector()
+        .iter()
+        .flat_map(|(key, count)| (0..*count).map(move |i| hash_of(&(1u8, key, i))))
+        .collect::<Vec<_>>();
+    [vertex_shingles, edge_shingles].concat()
+}
+
+fn signature(graph: &Graph, number_of_hashes: usize) -> Vec<u64> {
+    let shingles = shingles(graph);
+    (0..number_of_hashes as u64)
+        .map(|i_hash| {
+            let seed = mix(i_hash.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15));
+            shingles
+                .iter()
+                .map(|shingle| mix(shingle ^ seed))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect()
+}
+
+impl MinHashIndex {
+    pub(super) fn new(graphs: &[&Graph], bands: usize, rows: usize) -> Self {
+        let band_keys: Vec<Vec<u64>> = graphs
+            .par_iter()
+            .map(|graph| {
+                signature(graph, bands * rows)
+                    .chunks(rows)
+                    .map(hash_of)
+                    .collect()
+            })
+            .collect();
+        let mut buckets = vec![HashMap::new(); bands];
+        for (i_graph, keys) in band_keys.iter().enumerate() {
+            for (band, key) in buckets.iter_mut().zip(keys.iter()) {
+                band.entry(*key).or_insert_with(Vec::new).push(i_graph);
+            }
+        }
+        MinHashIndex { band_keys, buckets }
+    }
+
+    /// Indices of the graphs that share a bucket with the graph `i_graph`, including itself, in
+    /// ascending order
+    pub(super) fn similar(&self, i_graph: usize) -> Vec<usize> {
+        let mut similar: Vec<usize> = self.band_keys[i_graph]
+            .iter()
+            .zip(self.buckets.iter())
+            .flat_map(|(key, band)| band[key].iter().copied())
+            .collect();
+        similar.sort_unstable();
+        similar.dedup();
+        similar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpd::test_utils::make_path_graph;
+
+    #[test]
+    fn test_min_hash_index() {
+        let graphs = [
+            make_path_graph(0, &[1, 2, 3, 4, 5, 6]),
+            // Equal vectors
+            make_path_graph(1, &[1, 2, 3, 4, 5, 6]),
+            // Nearly equal
+            make_path_graph(2, &[1, 2, 3, 4, 5, 7]),
+            // Completely different
+            make_path_graph(3, &[11, 12, 13, 14, 15, 16]),
+        ];
+        let graphs: Vec<&Graph> = graphs.iter().collect();
+        let index = MinHashIndex::new(&graphs, 32, 2);
+        let similar = index.similar(0);
+        assert!(similar.contains(&0));
+        assert!(similar.contains(&1));
+        assert!(similar.contains(&2));
+        assert!(!similar.contains(&3));
+        assert_eq!(index.similar(3), vec![3]);
+
+        // A single band of many rows only keeps equal signatures
+        let index = MinHashIndex::new(&graphs, 1, 64);
+        assert_eq!(index.similar(0), vec![0, 1]);
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Lock-free union-find (disjoint set) of the elements `0..n`.
+///
+/// The root of a set is always its smallest element, so the representative of a set of
+/// candidates is the first one in the input order.
+pub(super) struct ConcurrentUnionFind {
+    parents: Vec<AtomicUsize>,
+}
+
+impl ConcurrentUnionFind {
+    pub(super) fn new(n: usize) -> Self {
+        ConcurrentUnionFind {
+            parents: (0..n).map(AtomicUsize::new).collect(),
+        }
+    }
+
+    /// Root of the set of `element`, with path halving
+    pub(super) fn find(&self, mut element: usize) -> usize {
+        loop {
+            let parent = self.parents[element].load(Ordering::Acquire);
+            if parent == element {
+                return element;
+            }
+            let grandparent = self.parents[parent].load(Ordering::Acquire);
+            // Failing is fine, another thread changed the parent in the meantime
+            let _ = self.parents[element].compare_exchange(
+                parent,
+                grandparent,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            );
+            element = grandparent;
+        }
+    }
+
+    /// Merges the sets of both elements
+    pub(super) fn union(&self, one: usize, other: usize) {
+        loop {
+            let one_root = self.find(one);
+            let other_root = self.find(other);
+            if one_root == other_root {
+                return;
+            }
+            let (root, child) = if one_root < other_root {
+                (one_root, other_root)
+            } else {
+                (other_root, one_root)
+            };
+            if self.parents[child]
+                .compare_exchange(child, root, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_concurrent_union_find() {
+        let union_find = ConcurrentUnionFind::new(1000);
+        // Classes by the remainder modulo 7
+        (0..1000usize).into_par_iter().for_each(|element| {
+            if element >= 7 {
+                union_find.union(element, element - 7);
+            }
+        });
+        for element in 0..1000 {
+            assert_eq!(union_find.find(element), element % 7);
+        }
+        // Merges the classes 5 and 4
+        union_find.union(999, 998);
+        assert_eq!(union_find.find(999), 4);
+        assert_eq!(union_find.find(5), 4);
+        assert_eq!(union_find.find(6), 6);
+    }
+}
@@ -1,18 +1,21 @@
 use std::time::Instant;
 
-use crate::data::graph::Graph;
+use crate::data::{graph::Graph, taxonomy::Taxonomy};
 
 use super::{
     candidate_generation::AlgoCandidateGeneration,
-    candidate_matching::{AlgoCandidateMatching, PatternResult},
-    graph_matching::AlgoGraphMatching,
+    candidate_matching::{AlgoCandidateMatching, CandidateClasses, PatternResult},
+    graph_matching::{AlgoGraphMatching, MatchingResult},
 };
 
 /// Runs the full Collaboration Pattern Discovery (CPD) pipeline:
 ///
-/// 1. **Candidate generation** using `AlgoCandidateGeneration`
+/// 1. **Candidate generation** using `AlgoCandidateGeneration`; the candidates are streamed
+///    into exact match classes (`CandidateClasses`), so only one graph per class is kept
 /// 2. **Candidate matching** across all graphs using `AlgoCandidateMatching`
 /// 3. **Pattern extraction** based on exact and relaxed support thresholds
+/// 4. **Generalization** (optional): with a `Taxonomy`, steps 2 and 3 are repeated with the
+///    vertex labels generalized level by level
 ///
 /// The function prints progress information unless `silence = true`.
 ///
@@ -32,8 +35,8 @@ use super::{
 ///
 /// ```rust
 /// use crate::cpd::CPDConfig;
-/// use crate::candidate_generation::AlgoCandidateGeneration;
-/// use crate::graph_matching::AlgoGraphMatching;
+/// use crate::candidate_generation::{AlgoCandidateGeneration, ContextConfig};
+/// use crate::graph_matching::{AlgoGraphMatching, SimilarityMeasure};
 /// use crate::data::graph::Graph;
 ///
 /// // Build two small graphs with identical 2-activity fully connected structure
@@ -52,16 +55,22 @@ use super::{
 ///
 /// // Generate all fully connected subsets of size 2
 /// let candidate_gen = AlgoCandidateGeneration::FullyConnected {
-///     activity_vertex_type: 2,
+///     activity_vertex_types: vec![2],
+///     activity_type_limits: HashMap::new(),
 ///     object_vertex_types: vec![],
+///     context: ContextConfig::default(),
 ///     min_number_of_activity_vertices: 2,
 ///     max_number_of_activity_vertices: 2,
+///     max_dropped_edges: 0,
 /// };
 ///
 /// // Match candidates using cosine-similarity graph matching
 /// let graph_match = AlgoGraphMatching::CosineSimilarity {
+///     measure: SimilarityMeasure::Cosine,
 ///     alpha: 0.5,
 ///     matching_threshold: 0.8,
+///     idf: None,
+///     label_similarity: None,
 /// };
 ///
 /// // Build CPD config (Parallel matching is selected by default)
@@ -85,10 +94,12 @@ use super::{
 ///
 /// # Notes
 ///
-/// - Candidate generation runs in **parallel**
+/// - Candidate generation runs in **parallel**, streamed in small batches into the classes
 /// - Candidate matching runs in **parallel**
 /// - Pattern IDs in the result are always rewritten to ensure they form a
 ///   contiguous sequence starting at zero.
+/// - Every pattern is reported at the most specific taxonomy level that reaches the support;
+///   the generalization of an already reported pattern is not reported again.
 #[derive(Debug)]
 pub struct CPDConfig {
     algo_candidate_generation: AlgoCandidateGeneration,
@@ -98,6 +109,7 @@ pub struct CPDConfig {
     support_relaxed: usize,
     silence: bool,
     compare_only_same_size: bool,
+    taxonomy: Option<Taxonomy>,
 }
 
 impl CPDConfig {
@@ -117,10 +129,21 @@ impl CPDConfig {
             support_relaxed,
             silence,
             compare_only_same_size,
+            taxonomy: None,
         }
     }
 
-    pub fn run(&self, graphs: &Vec<Graph>) -> Vec<PatternResult> {
+    /// Sets the strategy of the candidate matching (`Parallel` by default)
+    pub fn set_candidate_matching(&mut self, algo_candidate_matching: AlgoCandidateMatching) {
+        self.algo_candidate_matching = algo_candidate_matching;
+    }
+
+    /// Sets the label hierarchy for the generalized pattern matching
+    pub fn set_taxonomy(&mut self, taxonomy: Taxonomy) {
+        self.taxonomy = Some(taxonomy);
+    }
+
+    pub fn run(&self, graphs: &[Graph]) -> Vec<PatternResult> {
         if !self.silence {
             println!(
                 "1. Candidate generation : {:?}",
@@ -128,13 +151,17 @@ impl CPDConfig {
             );
         }
         let now = Instant::now();
-        let candidates = self.algo_candidate_generation.get_candidates(graphs);
+        let mut classes = CandidateClasses::new(self.compare_only_same_size);
+        self.algo_candidate_generation
+            .stream_candidates(graphs, |candidates| {
+                classes.extend(candidates, &self.algo_graph_matching)
+            });
         let delta = now.elapsed().as_millis();
         if !self.silence {
-            let all_candidates: Vec<_> = candidates.iter().flatten().flatten().collect();
             println!(
-                " -> Found {} candidates; took {}ms",
-                all_candidates.len(),
+                " -> Found {} candidates in {} exact match classes; took {}ms",
+                classes.number_of_candidates(),
+                classes.number_of_classes(),
                 delta
             );
             println!(
@@ -147,17 +174,185 @@ impl CPDConfig {
         }
 
         let now = Instant::now();
-        let result = self.algo_candidate_matching.run_matching(
-            &candidates,
+        self.algo_graph_matching.prepare(graphs, &classes);
+        let mut result = self.algo_candidate_matching.run_matching(
+            &classes,
             &self.algo_graph_matching,
             self.support_exact,
             self.support_relaxed,
-            self.compare_only_same_size,
         );
         let delta = now.elapsed().as_millis();
         if !self.silence {
             println!(" -> Found {} patterns; took {delta}ms", result.len());
         }
+        if let Some(taxonomy) = &self.taxonomy {
+            result = self.run_generalized(taxonomy, &classes, result);
+            for (id_gen, pattern_result) in result.iter_mut().enumerate() {
+                pattern_result.pattern.id = id_gen;
+            }
+        }
+        if !self.silence
+            && let AlgoGraphMatching::Cascade { stages: _, stats } = &self.algo_graph_matching
+        {
+            println!(" - Cascade statistics (pairs resolved per stage):");
+            println!("{stats}");
+        }
         result
     }
+
+    /// Matches the candidates with the labels generalized to every level of the taxonomy.
+    /// Patterns that are generalizations of a pattern of a lower level are dropped, unless the
+    /// generalization increases the frequencies.
+    fn run_generalized(
+        &self,
+        taxonomy: &Taxonomy,
+        classes: &CandidateClasses,
+        mut result: Vec<PatternResult>,
+    ) -> Vec<PatternResult> {
+        // Reported patterns, generalized to the current level
+        let mut reported = result.clone();
+        for level in 1..=taxonomy.max_level() {
+            let now = Instant::now();
+            let generalized_classes =
+                classes.generalized(taxonomy, level, &self.algo_graph_matching);
+            let patterns = self.algo_candidate_matching.run_matching(
+                &generalized_classes,
+                &self.algo_graph_matching,
+                self.support_exact,
+                self.support_relaxed,
+            );
+            reported = reported
+                .iter()
+                .map(|r| PatternResult {
+                    pattern: taxonomy.generalize(&r.pattern, level),
+                    ..*r
+                })
+                .collect();
+            let new_patterns: Vec<PatternResult> = patterns
+                .into_iter()
+                .filter(|pattern| {
+                    !reported.iter().any(|reported| {
+                        reported.frequency_exact >= pattern.frequency_exact
+                            && reported.frequency_relaxed >= pattern.frequency_relaxed
+                            && is_same_pattern(&reported.pattern, &pattern.pattern)
+                    })
+                })
+                .map(|pattern| PatternResult { level, ..pattern })
+                .collect();
+            if !self.silence {
+                println!(
+                    " - Taxonomy level {level}: {} new patterns; took {}ms",
+                    new_patterns.len(),
+                    now.elapsed().as_millis()
+                );
+            }
+            reported.extend(new_patterns.iter().cloned());
+            result.extend(new_patterns);
+        }
+        result
+    }
+}
+
+/// Whether two patterns are isomorphic, including their labels
+fn is_same_pattern(one_pattern: &Graph, other_pattern: &Graph) -> bool {
+    one_pattern.get_vertex_vector() == other_pattern.get_vertex_vector()
+        && one_pattern.get_edge_vector() == other_pattern.get_edge_vector()
+        && AlgoGraphMatching::VF2IsomorphismTest.match_graphs(one_pattern, other_pattern)
+            == MatchingResult::ExactMatch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpd::candidate_generation::ContextConfig;
+    use std::{collections::HashMap, fs};
+
+    #[test]
+    fn test_taxonomy_generalization() {
+        // Activity pairs (type 2) 1 -> 2 and 1 -> 3; labels 2 and 3 roll up to 10
+        let make_graph = |id, label| {
+            let mut g = Graph::new(id);
+            g.create_vertex_with_data(1, 2);
+            g.create_vertex_with_data(label, 2);
+            g.vertices[0].push(1, 0);
+            g
+        };
+        let graphs = vec![make_graph(0, 2), make_graph(1, 2), make_graph(2, 3)];
+        let path = std::env::temp_dir().join("cpd_test_config_taxonomy.txt");
+        fs::write(&path, "p 2 2 10\np 2 3 10\n").unwrap();
+        let taxonomy = Taxonomy::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut cpd = CPDConfig::new(
+            AlgoCandidateGeneration::FullyConnected {
+                activity_vertex_types: vec![2],
+                activity_type_limits: HashMap::new(),
+                object_vertex_types: vec![],
+                context: ContextConfig::default(),
+                min_number_of_activity_vertices: 2,
+                max_number_of_activity_vertices: 2,
+                max_dropped_edges: 0,
+            },
+            AlgoGraphMatching::VF2IsomorphismTest,
+            2,
+            usize::MAX,
+            true,
+            false,
+        );
+        let patterns = cpd.run(&graphs);
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].frequency_exact, 2);
+
+        cpd.set_taxonomy(taxonomy);
+        let patterns = cpd.run(&graphs);
+        // 1 -> 2 is frequent as is, 1 -> 10 only generalized; 1 -> 3 is not reported
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].level, 0);
+        assert_eq!(patterns[0].pattern.vertices[1].label, 2);
+        assert_eq!(patterns[1].level, 1);
+        assert_eq!(patterns[1].pattern.vertices[1].label, 10);
+        assert_eq!(patterns[1].frequency_exact, 3);
+        assert_eq!(patterns[1].pattern.id, 1);
+    }
+
+    #[test]
+    fn test_taxonomy_lowest_common_ancestor() {
+        // Activity pairs (type 2) 1 -> 3 and 1 -> 10 with the unbalanced hierarchy 3 -> 10 -> 20
+        let make_graph = |id, label| {
+            let mut g = Graph::new(id);
+            g.create_vertex_with_data(1, 2);
+            g.create_vertex_with_data(label, 2);
+            g.vertices[0].push(1, 0);
+            g
+        };
+        let graphs = vec![make_graph(0, 3), make_graph(1, 10)];
+        let path = std::env::temp_dir().join("cpd_test_config_taxonomy_lca.txt");
+        fs::write(&path, "p 2 3 10\np 2 10 20\n").unwrap();
+        let taxonomy = Taxonomy::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut cpd = CPDConfig::new(
+            AlgoCandidateGeneration::FullyConnected {
+                activity_vertex_types: vec![2],
+                activity_type_limits: HashMap::new(),
+                object_vertex_types: vec![],
+                context: ContextConfig::default(),
+                min_number_of_activity_vertices: 2,
+                max_number_of_activity_vertices: 2,
+                max_dropped_edges: 0,
+            },
+            AlgoGraphMatching::VF2IsomorphismTest,
+            2,
+            usize::MAX,
+            true,
+            false,
+        );
+        cpd.set_taxonomy(taxonomy);
+        let patterns = cpd.run(&graphs);
+        // Both candidates match at their lowest common ancestor 10, not only at the root 20
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].level, 1);
+        assert_eq!(patterns[0].pattern.vertices[1].label, 10);
+        assert_eq!(patterns[0].frequency_exact, 2);
+    }
 }
@@ -1,39 +1,156 @@
-use crate::data::graph::Graph;
-use petgraph::visit::EdgeRef;
-use petgraph::visit::NodeIndexable;
-use petgraph::{algo::isomorphism::is_isomorphic_matching, graph::DiGraph};
+mod cascade;
+mod context;
+mod ged;
+mod idf;
+mod label_similarity;
+mod mcs;
+
+use super::candidate_matching::CandidateClasses;
+use crate::data::graph::{Graph, GraphInvariants, SpectralMatrix};
+pub use cascade::{CascadeStage, CascadeStats};
+use cascade::{FilterResult, match_cascade, run_stages};
+use context::filter_context_relaxed;
+pub use ged::{
+    GEDEditCosts, GEDNormalization, TypeChangeCost, label_lower_bound, size_lower_bound,
+};
+use ged::{exact_ged, fast_ged, size_bound_within_threshold};
+pub use idf::{IdfSource, IdfWeights};
+pub use label_similarity::{LabelSimilarity, LabelSimilarityParseError};
+use mcs::{mcs_similarity_bound, mcs_size};
+use petgraph::algo::isomorphism::is_isomorphic_matching;
 use std::{
     collections::{HashMap, HashSet},
     hash::Hash,
+    sync::Arc,
+    time::Duration,
 };
 
 /// Defines the available algorithms for comparing two graphs and determining
 /// how similar they are.
 ///
 /// Currently, the implemented methods are:
-/// - `CosineSimilarity { alpha, matching_threshold }`
+/// - `CosineSimilarity { measure, alpha, matching_threshold, idf, label_similarity }`
 /// - `VF2IsomorphismTest`
-/// - `GEDFastHungarian { edit_costs, matching_threshold }`
+/// - `GEDFastHungarian { edit_costs, normalization, matching_threshold }`
+/// - `GEDExact { edit_costs, normalization, matching_threshold }`
+/// - `WLSubtreeKernel { iterations, matching_threshold }`
+/// - `Spectral { matrix, matching_threshold }`
+/// - `MaximumCommonSubgraph { time_limit, matching_threshold }`
+/// - `Cascade { stages, stats }`
+/// - `ContextRelaxed { activity_vertex_types, context_matching }`
 #[derive(Debug)]
 pub enum AlgoGraphMatching {
     /// Computes similarity based on the cosine similarity of vertex- and edge-frequency
-    /// vectors extracted from both graphs.
+    /// vectors extracted from both graphs, or on another similarity `measure` of the vectors.
     ///
     /// # Parameters
+    /// - `measure`: similarity function of two frequency vectors
     /// - `alpha`: weight of vertex similarity vs. edge similarity (0–1)
     /// - `matching_threshold`: minimum similarity for `RelaxedMatch`
-    CosineSimilarity { alpha: f64, matching_threshold: f64 },
+    /// - `idf`: optional inverse document frequency weights of the vector entries, so rare labels
+    ///   drive the similarity instead of ubiquitous ones
+    /// - `label_similarity`: optional similarities of vertex labels; with the `Cosine` measure,
+    ///   the soft cosine similarity is used, so near-synonymous labels count as a partial match.
+    ///   The other measures ignore it.
+    CosineSimilarity {
+        measure: SimilarityMeasure,
+        alpha: f64,
+        matching_threshold: f64,
+        idf: Option<IdfWeights>,
+        label_similarity: Option<Arc<LabelSimilarity>>,
+    },
 
     /// Determines exact graph isomorphism using the VF2 algorithm.
     /// Returns either `ExactMatch` or `NoMatch`.
     VF2IsomorphismTest,
 
-    /// Approximate graph edit distance using bipartite matching.
-    /// Considers node/edge insertions, deletions, and substitutions.
+    /// Approximate graph edit distance using bipartite matching (Riesen & Bunke).
+    /// Considers node/edge insertions, deletions, and substitutions of incoming and outgoing
+    /// edges; the distance is an upper bound of the exact GED. With a `normalization`, the
+    /// `matching_threshold` is relative to the size of the graphs.
     GEDFastHungarian {
         edit_costs: GEDEditCosts,
-        matching_threshold: usize,
+        normalization: GEDNormalization,
+        matching_threshold: f64,
+    },
+
+    /// Exact graph edit distance using a depth-first branch and bound search.
+    /// Feasible for small graphs; the search stops as soon as the distance is known to exceed
+    /// the (normalized) `matching_threshold`.
+    GEDExact {
+        edit_costs: GEDEditCosts,
+        normalization: GEDNormalization,
+        matching_threshold: f64,
+    },
+
+    /// Computes similarity based on the cosine similarity of the Weisfeiler-Lehman subtree
+    /// features of both graphs, i.e. the normalized WL subtree kernel. Unlike the vertex and edge
+    /// vectors, the features capture the directed neighbourhoods up to `iterations` hops.
+    ///
+    /// # Parameters
+    /// - `iterations`: number of WL iterations (0 only compares the vertex labels)
+    /// - `matching_threshold`: minimum similarity for `RelaxedMatch`
+    WLSubtreeKernel {
+        iterations: usize,
+        matching_threshold: f64,
     },
+
+    /// Compares the eigenvalue spectra of the label-weighted adjacency matrices or of their
+    /// normalized Laplacians. The similarity is `1 / (1 + d)`, where `d` is the Euclidean
+    /// distance of the spectra in descending order (the smaller one padded with zeros).
+    ///
+    /// # Parameters
+    /// - `matrix`: matrix whose eigenvalues are compared
+    /// - `matching_threshold`: minimum similarity for `RelaxedMatch`
+    Spectral {
+        matrix: SpectralMatrix,
+        matching_threshold: f64,
+    },
+
+    /// Scores how much of the graphs is shared: `|MCS| / max(|G1|, |G2|)`, where `|MCS|` is
+    /// the number of vertices of the maximum common connected subgraph (McSplit branch and bound)
+    /// that respects the vertex labels and types, and the edge labels and directions.
+    ///
+    /// # Parameters
+    /// - `time_limit`: maximum search time per pair; afterwards, the largest common subgraph
+    ///   found so far is used, i.e. the similarity may be underestimated
+    /// - `matching_threshold`: minimum similarity for `RelaxedMatch`
+    MaximumCommonSubgraph {
+        time_limit: Duration,
+        matching_threshold: f64,
+    },
+
+    /// Chains cheap filters before expensive matchers: each of the `stages` either rejects a
+    /// pair or passes it on, and VF2 is only run at the end to confirm exact matches. The
+    /// `stats` count how many pairs each stage resolved.
+    Cascade {
+        stages: Vec<CascadeStage>,
+        stats: CascadeStats,
+    },
+
+    /// Matches the activity core exactly and the object context relaxed: the subgraphs of the
+    /// `activity_vertex_types` (activity vertices and the edges between them) must be isomorphic
+    /// (VF2), then the `context_matching` classifies the object contexts (the other vertices,
+    /// the edges incident to them and the activity vertices they are attached to), e.g. "same
+    /// collaboration, slightly different context".
+    ContextRelaxed {
+        activity_vertex_types: Vec<usize>,
+        context_matching: Box<AlgoGraphMatching>,
+    },
+}
+
+/// Similarity functions of two frequency vectors `a` and `b` for `CosineSimilarity`; all return
+/// values between 0.0 and 1.0, and 0.0 if one of the vectors is empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimilarityMeasure {
+    /// `a·b / (|a| |b|)`
+    Cosine,
+    /// `Σ min(a, b) / Σ max(a, b)`
+    WeightedJaccard,
+    /// `2 Σ min(a, b) / (Σ a + Σ b)`
+    Dice,
+    /// `Σ min(a, b) / min(Σ a, Σ b)`, 1.0 if one vector is contained in the other
+    Overlap,
 }
 
 /// Result of comparing two graphs.
@@ -48,22 +165,185 @@ pub enum MatchingResult {
 }
 
 impl AlgoGraphMatching {
+    /// Computes data over all input graphs and candidates that the matching depends on, e.g. the
+    /// IDF weights. Called once before the candidates are matched.
+    pub fn prepare(&self, graphs: &[Graph], candidates: &CandidateClasses) {
+        match self {
+            AlgoGraphMatching::CosineSimilarity { idf: Some(idf), .. } => {
+                idf.prepare(graphs, candidates)
+            }
+            AlgoGraphMatching::Cascade { stages, stats: _ } => {
+                for stage in stages.iter() {
+                    if let CascadeStage::Matcher(algo_graph_matching) = stage {
+                        algo_graph_matching.prepare(graphs, candidates);
+                    }
+                }
+            }
+            AlgoGraphMatching::ContextRelaxed {
+                activity_vertex_types: _,
+                context_matching,
+            } => context_matching.prepare(graphs, candidates),
+            _ => {}
+        }
+    }
+
+    /// Whether two graphs with the invariants `one` and `other` may be a relaxed match. Graphs
+    /// with different invariants can never be an exact match.
+    ///
+    /// A cheap necessary condition per matcher:
+    /// - VF2 -> equal invariants
+    /// - GED -> the `size_lower_bound` is within the threshold
+    /// - Maximum common subgraph -> the common vertices per vertex type reach the threshold
+    /// - Cascade -> all matcher stages
+    /// - Context-relaxed -> equal numbers of vertices of every activity vertex type
+    /// - Otherwise always `true`
+    pub fn invariants_within_threshold(
+        &self,
+        one: &GraphInvariants,
+        other: &GraphInvariants,
+    ) -> bool {
+        match self {
+            AlgoGraphMatching::VF2IsomorphismTest => one == other,
+            AlgoGraphMatching::GEDFastHungarian {
+                edit_costs,
+                normalization,
+                matching_threshold,
+            }
+            | AlgoGraphMatching::GEDExact {
+                edit_costs,
+                normalization,
+                matching_threshold,
+            } => size_bound_within_threshold(
+                one,
+                other,
+                edit_costs,
+                normalization,
+                *matching_threshold,
+            ),
+            AlgoGraphMatching::MaximumCommonSubgraph {
+                time_limit: _,
+                matching_threshold,
+            } => mcs_similarity_bound(one, other) >= *matching_threshold,
+            AlgoGraphMatching::Cascade { stages, stats: _ } => {
+                stages.iter().all(|stage| match stage {
+                    CascadeStage::Matcher(algo_graph_matching) => {
+                        algo_graph_matching.invariants_within_threshold(one, other)
+                    }
+                    CascadeStage::LabelBound { .. } => true,
+                })
+            }
+            AlgoGraphMatching::ContextRelaxed {
+                activity_vertex_types,
+                context_matching: _,
+            } => {
+                // The invariants of the object contexts are unknown, but isomorphic activity
+                // cores have the same number of vertices of every activity vertex type
+                let activity_type_counts = |invariants: &GraphInvariants| {
+                    invariants
+                        .type_counts
+                        .iter()
+                        .filter(|(vertex_type, _)| activity_vertex_types.contains(vertex_type))
+                        .copied()
+                        .collect::<Vec<_>>()
+                };
+                activity_type_counts(one) == activity_type_counts(other)
+            }
+            AlgoGraphMatching::CosineSimilarity { .. }
+            | AlgoGraphMatching::WLSubtreeKernel { .. }
+            | AlgoGraphMatching::Spectral { .. } => true,
+        }
+    }
+
     /// Computes a floating-point similarity/distance score between two graphs.
     ///
     /// - `1.0` → very similar / identical (depending on algorithm)
     /// - `0.0` → completely dissimilar
-    /// - For the GED -> 0 → Equal graphs, values > 0 -> edit costs (distance)
+    /// - For the GED -> 0 → Equal graphs, values > 0 -> edit costs (distance), divided by the
+    ///   normalizer of the `normalization`; the exact GED returns infinity for distances above
+    ///   its `matching_threshold`
+    /// - For the cascade -> 1.0 if the graphs pass all stages, 0.0 otherwise
+    /// - For the context-relaxed matcher -> 1.0 if the activity cores are isomorphic and the
+    ///   `context_matching` accepts the graphs, 0.0 otherwise
     pub fn calc_distance(&self, one_graph: &Graph, other_graph: &Graph) -> f64 {
         match self {
             AlgoGraphMatching::CosineSimilarity {
+                measure,
                 alpha,
                 matching_threshold: _,
-            } => graph_cosine_similarity(one_graph, other_graph, *alpha),
+                idf,
+                label_similarity,
+            } => graph_vector_similarity(
+                one_graph,
+                other_graph,
+                *measure,
+                *alpha,
+                idf.as_ref(),
+                label_similarity.as_deref(),
+            ),
             AlgoGraphMatching::VF2IsomorphismTest => graph_vf2_isomorphism(one_graph, other_graph),
+            AlgoGraphMatching::WLSubtreeKernel {
+                iterations,
+                matching_threshold: _,
+            } => _calc_cosine_similarity(
+                &one_graph.get_wl_features(*iterations),
+                &other_graph.get_wl_features(*iterations),
+                |_| 1.0,
+            ),
+            AlgoGraphMatching::Spectral {
+                matrix,
+                matching_threshold: _,
+            } => graph_spectral_similarity(one_graph, other_graph, *matrix),
+            AlgoGraphMatching::MaximumCommonSubgraph {
+                time_limit,
+                matching_threshold: _,
+            } => {
+                let max_size = one_graph.vertices.len().max(other_graph.vertices.len());
+                if max_size == 0 {
+                    return 1.0;
+                }
+                mcs_size(one_graph, other_graph, *time_limit) as f64 / max_size as f64
+            }
             AlgoGraphMatching::GEDFastHungarian {
                 edit_costs,
+                normalization,
                 matching_threshold: _,
-            } => fast_ged(one_graph, other_graph, edit_costs) as f64,
+            } => {
+                fast_ged(one_graph, other_graph, edit_costs) as f64
+                    / normalization.normalizer(one_graph, other_graph, edit_costs)
+            }
+            AlgoGraphMatching::GEDExact {
+                edit_costs,
+                normalization,
+                matching_threshold,
+            } => {
+                let normalizer = normalization.normalizer(one_graph, other_graph, edit_costs);
+                // Largest absolute GED within the threshold (GEDs are integers)
+                const EPS: f64 = 1e-8;
+                let max_cost = (*matching_threshold * normalizer + EPS).floor() as usize;
+                exact_ged(one_graph, other_graph, edit_costs, Some(max_cost))
+                    .map_or(f64::INFINITY, |distance| distance as f64 / normalizer)
+            }
+            AlgoGraphMatching::Cascade { stages, stats: _ } => {
+                if run_stages(stages, one_graph, other_graph).is_ok() {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            AlgoGraphMatching::ContextRelaxed {
+                activity_vertex_types,
+                context_matching,
+            } => {
+                match filter_context_relaxed(
+                    activity_vertex_types,
+                    context_matching,
+                    one_graph,
+                    other_graph,
+                ) {
+                    FilterResult::Rejected => 0.0,
+                    _ => 1.0,
+                }
+            }
         }
     }
 
@@ -95,8 +375,11 @@ impl AlgoGraphMatching {
     ///
     /// ```rust
     /// let algo = AlgoGraphMatching::CosineSimilarity {
+    ///     measure: SimilarityMeasure::Cosine,
     ///     alpha: 0.5,
     ///     matching_threshold: 0.6,
+    ///     idf: None,
+    ///     label_similarity: None,
     /// };
     ///
     /// let r = algo.match_graphs(&graph_a, &graph_b);
@@ -108,71 +391,213 @@ impl AlgoGraphMatching {
     /// }
     /// ```
     pub fn match_graphs(&self, one_graph: &Graph, other_graph: &Graph) -> MatchingResult {
-        let distance = self.calc_distance(one_graph, other_graph);
+        match self {
+            AlgoGraphMatching::Cascade { stages, stats } => {
+                match_cascade(stages, Some(stats), one_graph, other_graph)
+            }
+            _ => self.match_graphs_uncounted(one_graph, other_graph),
+        }
+    }
+
+    /// Like `match_graphs`, but the pair is not counted in the statistics of the cascade, e.g.
+    /// for building the exact match classes of the candidates.
+    pub fn match_graphs_uncounted(&self, one_graph: &Graph, other_graph: &Graph) -> MatchingResult {
+        match self {
+            AlgoGraphMatching::Cascade { stages, stats: _ } => {
+                match_cascade(stages, None, one_graph, other_graph)
+            }
+            _ => match self.filter_graphs(one_graph, other_graph) {
+                FilterResult::Identical => {
+                    if matches!(self, AlgoGraphMatching::VF2IsomorphismTest)
+                        || graph_vf2_isomorphism(one_graph, other_graph) == 1.0
+                    {
+                        MatchingResult::ExactMatch
+                    } else {
+                        MatchingResult::RelaxedMatch
+                    }
+                }
+                FilterResult::Similar => MatchingResult::RelaxedMatch,
+                FilterResult::Rejected => MatchingResult::NoMatch,
+            },
+        }
+    }
+
+    /// Classifies two graphs by their distance without confirming exact matches with VF2.
+    fn filter_graphs(&self, one_graph: &Graph, other_graph: &Graph) -> FilterResult {
         match self {
             AlgoGraphMatching::CosineSimilarity {
+                measure: _,
                 alpha: _,
                 matching_threshold,
+                idf: _,
+                label_similarity: _,
+            }
+            | AlgoGraphMatching::WLSubtreeKernel {
+                iterations: _,
+                matching_threshold,
+            }
+            | AlgoGraphMatching::Spectral {
+                matrix: _,
+                matching_threshold,
+            }
+            | AlgoGraphMatching::MaximumCommonSubgraph {
+                time_limit: _,
+                matching_threshold,
             } => {
+                let distance = self.calc_distance(one_graph, other_graph);
                 const EPS: f64 = 1e-8;
                 if distance >= 1.0 - EPS {
-                    if AlgoGraphMatching::VF2IsomorphismTest.match_graphs(one_graph, other_graph)
-                        == MatchingResult::ExactMatch
-                    {
-                        MatchingResult::ExactMatch
-                    } else {
-                        MatchingResult::RelaxedMatch
-                    }
+                    FilterResult::Identical
                 } else if distance >= *matching_threshold {
-                    MatchingResult::RelaxedMatch
+                    FilterResult::Similar
                 } else {
-                    MatchingResult::NoMatch
+                    FilterResult::Rejected
                 }
             }
             AlgoGraphMatching::VF2IsomorphismTest => {
-                if distance == 1.0 {
-                    MatchingResult::ExactMatch
+                if graph_vf2_isomorphism(one_graph, other_graph) == 1.0 {
+                    FilterResult::Identical
                 } else {
-                    MatchingResult::NoMatch
+                    FilterResult::Rejected
                 }
             }
             AlgoGraphMatching::GEDFastHungarian {
-                edit_costs: _,
+                edit_costs,
+                normalization,
+                matching_threshold,
+            }
+            | AlgoGraphMatching::GEDExact {
+                edit_costs,
+                normalization,
                 matching_threshold,
             } => {
-                let distance = distance.round() as usize;
-                if distance == 0 {
-                    if AlgoGraphMatching::VF2IsomorphismTest.match_graphs(one_graph, other_graph)
-                        == MatchingResult::ExactMatch
-                    {
-                        MatchingResult::ExactMatch
-                    } else {
-                        MatchingResult::RelaxedMatch
-                    }
+                // Skip the GED for pairs whose lower bounds already exceed the threshold
+                let normalizer = normalization.normalizer(one_graph, other_graph, edit_costs);
+                let exceeds_threshold =
+                    |bound: usize| bound as f64 / normalizer > *matching_threshold;
+                if exceeds_threshold(size_lower_bound(one_graph, other_graph, edit_costs))
+                    || exceeds_threshold(label_lower_bound(one_graph, other_graph, edit_costs))
+                {
+                    return FilterResult::Rejected;
+                }
+                let distance = self.calc_distance(one_graph, other_graph);
+                if distance == 0.0 {
+                    FilterResult::Identical
                 } else if distance <= *matching_threshold {
-                    MatchingResult::RelaxedMatch
+                    FilterResult::Similar
                 } else {
-                    MatchingResult::NoMatch
+                    FilterResult::Rejected
+                }
+            }
+            AlgoGraphMatching::Cascade { stages, stats: _ } => {
+                match run_stages(stages, one_graph, other_graph) {
+                    Ok(true) => FilterResult::Identical,
+                    Ok(false) => FilterResult::Similar,
+                    Err(_) => FilterResult::Rejected,
                 }
             }
+            AlgoGraphMatching::ContextRelaxed {
+                activity_vertex_types,
+                context_matching,
+            } => filter_context_relaxed(
+                activity_vertex_types,
+                context_matching,
+                one_graph,
+                other_graph,
+            ),
         }
     }
 }
 
-fn graph_cosine_similarity(one_graph: &Graph, other_graph: &Graph, alpha: f64) -> f64 {
+fn graph_vector_similarity(
+    one_graph: &Graph,
+    other_graph: &Graph,
+    measure: SimilarityMeasure,
+    alpha: f64,
+    idf: Option<&IdfWeights>,
+    label_similarity: Option<&LabelSimilarity>,
+) -> f64 {
     let one_graph_vertex_vector = one_graph.get_vertex_vector();
     let other_graph_vertex_vector = other_graph.get_vertex_vector();
     let one_graph_edge_vector = one_graph.get_edge_vector();
     let other_graph_edge_vector = other_graph.get_edge_vector();
-    let sim_vertices =
-        _calc_cosine_similarity(&one_graph_vertex_vector, &other_graph_vertex_vector);
-    let sim_edges = _calc_cosine_similarity(&one_graph_edge_vector, &other_graph_edge_vector);
+    if measure == SimilarityMeasure::Cosine
+        && let Some(label_similarity) = label_similarity
+    {
+        let sim_vertices = _calc_soft_cosine_similarity(
+            &one_graph_vertex_vector,
+            &other_graph_vertex_vector,
+            |key| idf.map_or(1.0, |idf| idf.vertex_weight(key)),
+            |a, b| {
+                if a.1 != b.1 {
+                    return 0.0;
+                }
+                label_similarity.similarity(a.0, b.0)
+            },
+        );
+        let sim_edges = _calc_soft_cosine_similarity(
+            &one_graph_edge_vector,
+            &other_graph_edge_vector,
+            |key| idf.map_or(1.0, |idf| idf.edge_weight(key)),
+            |a, b| {
+                if a.1 != b.1 || a.2 != b.2 || a.4 != b.4 {
+                    return 0.0;
+                }
+                label_similarity.similarity(a.0, b.0) * label_similarity.similarity(a.3, b.3)
+            },
+        );
+        return alpha * sim_vertices + (1.0 - alpha) * sim_edges;
+    }
+    let sim_vertices = _calc_vector_similarity(
+        measure,
+        &one_graph_vertex_vector,
+        &other_graph_vertex_vector,
+        |key| idf.map_or(1.0, |idf| idf.vertex_weight(key)),
+    );
+    let sim_edges = _calc_vector_similarity(
+        measure,
+        &one_graph_edge_vector,
+        &other_graph_edge_vector,
+        |key| idf.map_or(1.0, |idf| idf.edge_weight(key)),
+    );
     alpha * sim_vertices + (1.0 - alpha) * sim_edges
 }
 
-fn _calc_cosine_similarity<T: Eq + Hash>(
+/// Similarity of two frequency vectors whose entries are multiplied by the `weight` of their key
+fn _calc_vector_similarity<T: Eq + Hash, W: Fn(&T) -> f64>(
+    measure: SimilarityMeasure,
     one_vec: &HashMap<T, usize>,
     other_vec: &HashMap<T, usize>,
+    weight: W,
+) -> f64 {
+    if measure == SimilarityMeasure::Cosine {
+        return _calc_cosine_similarity(one_vec, other_vec, weight);
+    }
+    let weighted_sum = |vec: &HashMap<T, usize>| -> f64 {
+        vec.iter().map(|(key, v)| *v as f64 * weight(key)).sum()
+    };
+    let sum_one = weighted_sum(one_vec);
+    let sum_other = weighted_sum(other_vec);
+    if sum_one == 0.0 || sum_other == 0.0 {
+        return 0.0;
+    }
+    let sum_min: f64 = one_vec
+        .iter()
+        .map(|(key, v1)| (*v1).min(*other_vec.get(key).unwrap_or(&0)) as f64 * weight(key))
+        .sum();
+    match measure {
+        SimilarityMeasure::Cosine => unreachable!(),
+        // Σ max = Σ a + Σ b - Σ min
+        SimilarityMeasure::WeightedJaccard => sum_min / (sum_one + sum_other - sum_min),
+        SimilarityMeasure::Dice => 2.0 * sum_min / (sum_one + sum_other),
+        SimilarityMeasure::Overlap => sum_min / sum_one.min(sum_other),
+    }
+}
+
+fn _calc_cosine_similarity<T: Eq + Hash, W: Fn(&T) -> f64>(
+    one_vec: &HashMap<T, usize>,
+    other_vec: &HashMap<T, usize>,
+    weight: W,
 ) -> f64 {
     let mut keys = HashSet::new();
     keys.extend(one_vec.keys());
@@ -182,8 +607,9 @@ fn _calc_cosine_similarity<T: Eq + Hash>(
     let mut norm_other = 0.0f64;
 
     for key in keys {
-        let v1 = *one_vec.get(key).unwrap_or(&0) as f64;
-        let v2 = *other_vec.get(key).unwrap_or(&0) as f64;
+        let w = weight(key);
+        let v1 = *one_vec.get(key).unwrap_or(&0) as f64 * w;
+        let v2 = *other_vec.get(key).unwrap_or(&0) as f64 * w;
 
         dot += v1 * v2;
         norm_one += v1 * v1;
@@ -197,6 +623,60 @@ fn _calc_cosine_similarity<T: Eq + Hash>(
     dot / (norm_one.sqrt() * norm_other.sqrt())
 }
 
+/// Soft cosine similarity `a·S·b / sqrt(a·S·a · b·S·b)` of two frequency vectors, where the
+/// matrix `S` holds the `key_similarity` of all pairs of keys
+fn _calc_soft_cosine_similarity<T, W, S>(
+    one_vec: &HashMap<T, usize>,
+    other_vec: &HashMap<T, usize>,
+    weight: W,
+    key_similarity: S,
+) -> f64
+where
+    T: Eq + Hash,
+    W: Fn(&T) -> f64,
+    S: Fn(&T, &T) -> f64,
+{
+    let one: Vec<(&T, f64)> = one_vec
+        .iter()
+        .map(|(key, v)| (key, *v as f64 * weight(key)))
+        .collect();
+    let other: Vec<(&T, f64)> = other_vec
+        .iter()
+        .map(|(key, v)| (key, *v as f64 * weight(key)))
+        .collect();
+    let product = |a: &[(&T, f64)], b: &[(&T, f64)]| -> f64 {
+        let mut sum = 0.0;
+        for (key_a, v_a) in a {
+            for (key_b, v_b) in b {
+                sum += v_a * v_b * key_similarity(key_a, key_b);
+            }
+        }
+        sum
+    };
+    let norm_one = product(&one, &one);
+    let norm_other = product(&other, &other);
+    if norm_one == 0.0 || norm_other == 0.0 {
+        return 0.0;
+    }
+    (product(&one, &other) / (norm_one.sqrt() * norm_other.sqrt())).min(1.0)
+}
+
+fn graph_spectral_similarity(
+    one_graph: &Graph,
+    other_graph: &Graph,
+    matrix: SpectralMatrix,
+) -> f64 {
+    let one_spectrum = one_graph.get_spectrum(matrix);
+    let other_spectrum = other_graph.get_spectrum(matrix);
+    let length = one_spectrum.len().max(other_spectrum.len());
+    let eigenvalue = |spectrum: &[f64], i: usize| *spectrum.get(i).unwrap_or(&0.0);
+    let distance = (0..length)
+        .map(|i| (eigenvalue(&one_spectrum, i) - eigenvalue(&other_spectrum, i)).powi(2))
+        .sum::<f64>()
+        .sqrt();
+    1.0 / (1.0 + distance)
+}
+
 fn graph_vf2_isomorphism(one_graph: &Graph, other_graph: &Graph) -> f64 {
     let one_di_graph = one_graph.get_digraph();
     let other_di_graph = other_graph.get_digraph();
@@ -212,124 +692,6 @@ fn graph_vf2_isomorphism(one_graph: &Graph, other_graph: &Graph) -> f64 {
     if iso_exists { 1.0 } else { 0.0 }
 }
 
-/// Costs for Graph Edit Distance (GED)
-#[derive(Clone, Debug)]
-pub struct GEDEditCosts {
-    pub node_sub: usize,
-    pub node_ins: usize,
-    pub node_del: usize,
-    pub edge_sub: usize,
-    pub edge_ins: usize,
-    pub edge_del: usize,
-}
-
-impl Default for GEDEditCosts {
-    fn default() -> Self {
-        Self {
-            node_sub: 1,
-            node_ins: 1,
-            node_del: 1,
-            edge_sub: 1,
-            edge_ins: 1,
-            edge_del: 1,
-        }
-    }
-}
-
-/// Fast approximate graph edit distance using bipartite matching
-fn fast_ged(one_graph: &Graph, other_graph: &Graph, edit_costs: &GEDEditCosts) -> usize {
-    let g1 = one_graph.get_digraph();
-    let g2 = other_graph.get_digraph();
-    let n1 = g1.node_count();
-    let n2 = g2.node_count();
-    let max_n = n1.max(n2);
-    let height = max_n;
-    let width = max_n;
-
-    let mut cost_matrix = vec![0usize; height * width];
-    // Fill cost matrix with node substitution + edge differences
-    for i in 0..height {
-        for j in 0..width {
-            let cost = if i < n1 && j < n2 {
-                // real node substitution
-                let (l1, t1) = g1.node_weight(g1.from_index(i)).unwrap();
-                let (l2, t2) = g2.node_weight(g2.from_index(j)).unwrap();
-
-                let mut c = if l1 == l2 && t1 == t2 {
-                    0
-                } else {
-                    edit_costs.node_sub
-                };
-
-                c += edge_penalty(&g1, &g2, i, j, edit_costs);
-
-                c
-            } else if i < n1 {
-                // row = real node in G1, col = dummy → delete node i
-                edit_costs.node_del
-            } else {
-                // row = dummy, col = real node in G2 → insert node j
-                edit_costs.node_ins
-            };
-
-            cost_matrix[i * width + j] = cost;
-        }
-    }
-
-    let assignment = hungarian::minimize(&cost_matrix, height, width);
-    let mut total: usize = 0;
-    for (i, maybe_j) in assignment.iter().enumerate() {
-        if let Some(j) = maybe_j {
-            total += cost_matrix[i * width + j];
-        }
-    }
-
-    total
-}
-
-/// Compare outgoing edges of node i in g1 vs node j in g2
-fn edge_penalty(
-    g1: &DiGraph<(usize, usize), usize>,
-    g2: &DiGraph<(usize, usize), usize>,
-    i: usize,
-    j: usize,
-    edit_costs: &GEDEditCosts,
-) -> usize {
-    let ni = g1.from_index(i);
-    let nj = g2.from_index(j);
-
-    let mut penalty = 0;
-
-    // Outgoing edges
-    let mut g1_edges = Vec::new();
-    for e in g1.edges(ni) {
-        g1_edges.push((e.target().index(), *e.weight()));
-    }
-
-    let mut g2_edges = Vec::new();
-    for e in g2.edges(nj) {
-        g2_edges.push((e.target().index(), *e.weight()));
-    }
-
-    // Compare counts
-    let d = (g1_edges.len() as isize - g2_edges.len() as isize).unsigned_abs();
-    penalty += d * edit_costs.edge_ins;
-
-    // Compare overlapping edges
-    let m = usize::min(g1_edges.len(), g2_edges.len());
-
-    for k in 0..m {
-        let (_, w1) = g1_edges[k];
-        let (_, w2) = g2_edges[k];
-
-        if w1 != w2 {
-            penalty += edit_costs.edge_sub;
-        }
-    }
-
-    penalty
-}
-
 #[cfg(test)]
 mod tests {
     use crate::data::graph::Graph;
@@ -360,16 +722,22 @@ mod tests {
         other_eq_graph.vertices.get_mut(3).unwrap().push(2, 0);
         assert_eq!(
             AlgoGraphMatching::CosineSimilarity {
+                measure: SimilarityMeasure::Cosine,
                 alpha: 0.5,
-                matching_threshold: 1.0
+                matching_threshold: 1.0,
+                idf: None,
+                label_similarity: None,
             }
             .match_graphs(&one_graph, &other_eq_graph,),
             MatchingResult::ExactMatch
         );
         assert_eq!(
             AlgoGraphMatching::CosineSimilarity {
+                measure: SimilarityMeasure::Cosine,
                 alpha: 0.5,
-                matching_threshold: 0.5
+                matching_threshold: 0.5,
+                idf: None,
+                label_similarity: None,
             }
             .match_graphs(&one_graph, &other_eq_graph,),
             MatchingResult::ExactMatch
@@ -388,22 +756,258 @@ mod tests {
 
         assert_eq!(
             AlgoGraphMatching::CosineSimilarity {
+                measure: SimilarityMeasure::Cosine,
                 alpha: 0.5,
-                matching_threshold: 0.6
+                matching_threshold: 0.6,
+                idf: None,
+                label_similarity: None,
             }
             .match_graphs(&one_graph, &other_graph),
             MatchingResult::NoMatch
         );
         assert_eq!(
             AlgoGraphMatching::CosineSimilarity {
+                measure: SimilarityMeasure::Cosine,
                 alpha: 0.5,
-                matching_threshold: 0.5
+                matching_threshold: 0.5,
+                idf: None,
+                label_similarity: None,
             }
             .match_graphs(&one_graph, &other_graph),
             MatchingResult::RelaxedMatch
         );
     }
 
+    #[test]
+    fn test_vector_similarity_measures() {
+        let one_vec = HashMap::from([(1, 2), (2, 1), (3, 1)]);
+        let other_vec = HashMap::from([(1, 1), (2, 1), (4, 2)]);
+        // Σ min = 2, Σ max = 6, Σ a = Σ b = 4
+        let similarity = |measure| _calc_vector_similarity(measure, &one_vec, &other_vec, |_| 1.0);
+        assert!((similarity(SimilarityMeasure::WeightedJaccard) - 2.0 / 6.0).abs() < 1e-8);
+        assert!((similarity(SimilarityMeasure::Dice) - 0.5).abs() < 1e-8);
+        assert!((similarity(SimilarityMeasure::Overlap) - 0.5).abs() < 1e-8);
+        assert!((similarity(SimilarityMeasure::Cosine) - 3.0 / 6.0).abs() < 1e-8);
+
+        // Contained vectors overlap completely
+        let sub_vec = HashMap::from([(1, 1), (2, 1)]);
+        assert_eq!(
+            _calc_vector_similarity(SimilarityMeasure::Overlap, &sub_vec, &one_vec, |_| 1.0),
+            1.0
+        );
+        for measure in [
+            SimilarityMeasure::Cosine,
+            SimilarityMeasure::WeightedJaccard,
+            SimilarityMeasure::Dice,
+            SimilarityMeasure::Overlap,
+        ] {
+            assert!(
+                (_calc_vector_similarity(measure, &one_vec, &one_vec, |_| 1.0) - 1.0).abs() < 1e-8
+            );
+            assert_eq!(
+                _calc_vector_similarity(measure, &HashMap::<usize, usize>::new(), &one_vec, |_| {
+                    1.0
+                }),
+                0.0
+            );
+        }
+    }
+
+    #[test]
+    fn test_idf_weighted_vector_similarity() {
+        // Every graph contains the ubiquitous label 1
+        let graphs: Vec<Graph> = [2, 3, 2]
+            .into_iter()
+            .enumerate()
+            .map(|(id, label)| {
+                let mut graph = Graph::new(id);
+                graph.create_vertex_with_data(1, 0);
+                graph.create_vertex_with_data(label, 0);
+                graph
+            })
+            .collect();
+        let algo = |idf| AlgoGraphMatching::CosineSimilarity {
+            measure: SimilarityMeasure::Cosine,
+            alpha: 1.0,
+            matching_threshold: 0.45,
+            idf,
+            label_similarity: None,
+        };
+        let unweighted = algo(None);
+        let weighted = algo(Some(IdfWeights::new(IdfSource::Graphs)));
+        weighted.prepare(&graphs, &CandidateClasses::default());
+
+        assert!((unweighted.calc_distance(&graphs[0], &graphs[1]) - 0.5).abs() < 1e-8);
+        assert_eq!(
+            unweighted.match_graphs(&graphs[0], &graphs[1]),
+            MatchingResult::RelaxedMatch
+        );
+        assert!(weighted.calc_distance(&graphs[0], &graphs[1]) < 0.45);
+        assert_eq!(
+            weighted.match_graphs(&graphs[0], &graphs[1]),
+            MatchingResult::NoMatch
+        );
+        assert_eq!(
+            weighted.match_graphs(&graphs[0], &graphs[2]),
+            MatchingResult::ExactMatch
+        );
+    }
+
+    #[test]
+    fn test_soft_cosine_similarity() {
+        // "Approve Invoice" (3) and "Approve Invoice (manual)" (4)
+        let mut g1 = Graph::new(1);
+        g1.create_vertex_with_data(1, 0);
+        g1.create_vertex_with_data(3, 0);
+        g1.vertices.get_mut(0).unwrap().push(1, 0);
+        let mut g2 = Graph::new(2);
+        g2.create_vertex_with_data(1, 0);
+        g2.create_vertex_with_data(4, 0);
+        g2.vertices.get_mut(0).unwrap().push(1, 0);
+
+        let mut label_similarity = LabelSimilarity::default();
+        label_similarity.insert(3, 4, 0.8);
+        let algo = |label_similarity| AlgoGraphMatching::CosineSimilarity {
+            measure: SimilarityMeasure::Cosine,
+            alpha: 0.5,
+            matching_threshold: 0.8,
+            idf: None,
+            label_similarity,
+        };
+        let cosine = algo(None);
+        let soft_cosine = algo(Some(Arc::new(label_similarity)));
+
+        // Vertices: 1.8 / 2, edges: 0.8
+        assert!((soft_cosine.calc_distance(&g1, &g2) - 0.85).abs() < 1e-8);
+        assert!((cosine.calc_distance(&g1, &g2) - 0.25).abs() < 1e-8);
+        assert_eq!(
+            soft_cosine.match_graphs(&g1, &g2),
+            MatchingResult::RelaxedMatch
+        );
+        assert_eq!(cosine.match_graphs(&g1, &g2), MatchingResult::NoMatch);
+        assert_eq!(
+            soft_cosine.match_graphs(&g1, &g1),
+            MatchingResult::ExactMatch
+        );
+    }
+
+    #[test]
+    fn test_wl_subtree_kernel() {
+        // Same vertex and edge vectors: 0 -> 1, 2 -> 3 vs. 0 -> 1 -> 2 (and 3)
+        let mut one_graph = Graph::new(1);
+        let mut other_graph = Graph::new(2);
+        for graph in [&mut one_graph, &mut other_graph] {
+            for _ in 0..4 {
+                graph.create_vertex_with_data(1, 2);
+            }
+            graph.vertices.get_mut(0).unwrap().push(1, 0);
+        }
+        one_graph.vertices.get_mut(2).unwrap().push(3, 0);
+        other_graph.vertices.get_mut(1).unwrap().push(2, 0);
+
+        let cosine = AlgoGraphMatching::CosineSimilarity {
+            measure: SimilarityMeasure::Cosine,
+            alpha: 0.5,
+            matching_threshold: 0.9,
+            idf: None,
+            label_similarity: None,
+        };
+        assert_eq!(cosine.calc_distance(&one_graph, &other_graph), 1.0);
+
+        let wl = AlgoGraphMatching::WLSubtreeKernel {
+            iterations: 2,
+            matching_threshold: 0.9,
+        };
+        let similarity = wl.calc_distance(&one_graph, &other_graph);
+        assert!(similarity < 0.9 && similarity > 0.0);
+        assert_eq!(
+            wl.match_graphs(&one_graph, &other_graph),
+            MatchingResult::NoMatch
+        );
+        assert!((wl.calc_distance(&one_graph, &one_graph) - 1.0).abs() < 1e-8);
+        assert_eq!(
+            wl.match_graphs(&one_graph, &one_graph),
+            MatchingResult::ExactMatch
+        );
+
+        // Without iterations, only the vertex labels are compared
+        let wl = AlgoGraphMatching::WLSubtreeKernel {
+            iterations: 0,
+            matching_threshold: 0.9,
+        };
+        assert_eq!(
+            wl.match_graphs(&one_graph, &other_graph),
+            MatchingResult::RelaxedMatch
+        );
+    }
+
+    #[test]
+    fn test_spectral_similarity() {
+        // Triangle 0 -> 1 -> 2 -> 0 and path 0 -> 1 -> 2 with equal labels
+        let mut triangle = Graph::new(1);
+        let mut path = Graph::new(2);
+        for graph in [&mut triangle, &mut path] {
+            graph.create_vertex_with_data(1, 2);
+            graph.create_vertex_with_data(1, 2);
+            graph.create_vertex_with_data(1, 2);
+            graph.vertices.get_mut(0).unwrap().push(1, 0);
+            graph.vertices.get_mut(1).unwrap().push(2, 0);
+        }
+        triangle.vertices.get_mut(2).unwrap().push(0, 0);
+        // Path with another label in the middle
+        let mut other_path = Graph::new(3);
+        other_path.create_vertex_with_data(1, 2);
+        other_path.create_vertex_with_data(5, 2);
+        other_path.create_vertex_with_data(1, 2);
+        other_path.vertices.get_mut(0).unwrap().push(1, 0);
+        other_path.vertices.get_mut(1).unwrap().push(2, 0);
+
+        for matrix in [
+            SpectralMatrix::Adjacency,
+            SpectralMatrix::NormalizedLaplacian,
+        ] {
+            let algo = AlgoGraphMatching::Spectral {
+                matrix,
+                matching_threshold: 0.5,
+            };
+            assert!((algo.calc_distance(&path, &path) - 1.0).abs() < 1e-8);
+            assert_eq!(algo.match_graphs(&path, &path), MatchingResult::ExactMatch);
+            assert!(algo.calc_distance(&path, &triangle) < 1.0 - 1e-8);
+            assert!(algo.calc_distance(&path, &other_path) < 1.0 - 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_mcs_similarity() {
+        // Activities 1 -> 2 -> 3 with an object; the other graph lacks activity 3
+        let mut g1 = Graph::new(1);
+        g1.create_vertex_with_data(1, 2);
+        g1.create_vertex_with_data(2, 2);
+        g1.create_vertex_with_data(3, 2);
+        g1.create_vertex_with_data(9, 4);
+        g1.vertices.get_mut(0).unwrap().push(1, 0);
+        g1.vertices.get_mut(1).unwrap().push(2, 0);
+        g1.vertices.get_mut(1).unwrap().push(3, 1);
+        let mut g2 = Graph::new(2);
+        g2.create_vertex_with_data(1, 2);
+        g2.create_vertex_with_data(2, 2);
+        g2.create_vertex_with_data(9, 4);
+        g2.vertices.get_mut(0).unwrap().push(1, 0);
+        g2.vertices.get_mut(1).unwrap().push(2, 1);
+
+        let algo = |matching_threshold| AlgoGraphMatching::MaximumCommonSubgraph {
+            time_limit: Duration::from_secs(10),
+            matching_threshold,
+        };
+        assert!((algo(0.7).calc_distance(&g1, &g2) - 0.75).abs() < 1e-8);
+        assert_eq!(
+            algo(0.7).match_graphs(&g1, &g2),
+            MatchingResult::RelaxedMatch
+        );
+        assert_eq!(algo(0.8).match_graphs(&g1, &g2), MatchingResult::NoMatch);
+        assert_eq!(algo(0.8).match_graphs(&g1, &g1), MatchingResult::ExactMatch);
+    }
+
     #[test]
     fn test_vf2() {
         let mut one_graph = Graph::new(1);
@@ -511,7 +1115,8 @@ mod tests {
 
         let algo = AlgoGraphMatching::GEDFastHungarian {
             edit_costs: GEDEditCosts::default(),
-            matching_threshold: 1,
+            normalization: GEDNormalization::Absolute,
+            matching_threshold: 1.0,
         };
 
         assert_eq!(algo.match_graphs(&g1, &g2), MatchingResult::ExactMatch);
@@ -529,7 +1134,8 @@ mod tests {
 
         let algo = AlgoGraphMatching::GEDFastHungarian {
             edit_costs: GEDEditCosts::default(),
-            matching_threshold: 1,
+            normalization: GEDNormalization::Absolute,
+            matching_threshold: 1.0,
         };
 
         // Substitution of 1 node → distance = 1 → within threshold
@@ -547,7 +1153,8 @@ mod tests {
 
         let algo = AlgoGraphMatching::GEDFastHungarian {
             edit_costs: GEDEditCosts::default(),
-            matching_threshold: 1,
+            normalization: GEDNormalization::Absolute,
+            matching_threshold: 1.0,
         };
 
         // More edits than threshold → NoMatch
@@ -570,7 +1177,8 @@ mod tests {
 
         let algo = AlgoGraphMatching::GEDFastHungarian {
             edit_costs: GEDEditCosts::default(),
-            matching_threshold: 1,
+            normalization: GEDNormalization::Absolute,
+            matching_threshold: 1.0,
         };
 
         assert_eq!(algo.match_graphs(&g1, &g2), MatchingResult::ExactMatch);
@@ -590,7 +1198,8 @@ mod tests {
 
         let algo = AlgoGraphMatching::GEDFastHungarian {
             edit_costs: GEDEditCosts::default(),
-            matching_threshold: 1,
+            normalization: GEDNormalization::Absolute,
+            matching_threshold: 1.0,
         };
 
         // 1 edge substitution → distance = 1 → within threshold → RelaxedMatch
@@ -611,7 +1220,8 @@ mod tests {
 
         let algo = AlgoGraphMatching::GEDFastHungarian {
             edit_costs: GEDEditCosts::default(),
-            matching_threshold: 1,
+            normalization: GEDNormalization::Absolute,
+            matching_threshold: 1.0,
         };
 
         // 1 edge deleted → distance = 1 → within threshold → RelaxedMatch
@@ -636,10 +1246,101 @@ mod tests {
 
         let algo = AlgoGraphMatching::GEDFastHungarian {
             edit_costs: GEDEditCosts::default(),
-            matching_threshold: 1,
+            normalization: GEDNormalization::Absolute,
+            matching_threshold: 1.0,
         };
 
         // multiple edge substitutions → distance > threshold → NoMatch
         assert_eq!(algo.match_graphs(&g1, &g2), MatchingResult::NoMatch);
     }
+
+    #[test]
+    fn test_exact_ged_matching() {
+        let mut g1 = Graph::new(1);
+        g1.create_vertex_with_data(1, 2);
+        g1.create_vertex_with_data(2, 3);
+        g1.create_vertex_with_data(3, 3);
+        g1.vertices.get_mut(0).unwrap().push(1, 5);
+        g1.vertices.get_mut(0).unwrap().push(2, 5);
+
+        let mut g2 = Graph::new(2);
+        g2.create_vertex_with_data(3, 3);
+        g2.create_vertex_with_data(1, 2);
+        g2.create_vertex_with_data(2, 3);
+        g2.vertices.get_mut(1).unwrap().push(0, 5);
+        g2.vertices.get_mut(1).unwrap().push(2, 5);
+
+        let mut g3 = Graph::new(3);
+        g3.create_vertex_with_data(1, 2);
+        g3.create_vertex_with_data(2, 3);
+        g3.create_vertex_with_data(4, 3);
+        g3.vertices.get_mut(0).unwrap().push(1, 5);
+        g3.vertices.get_mut(2).unwrap().push(0, 5);
+
+        let algo = AlgoGraphMatching::GEDExact {
+            edit_costs: GEDEditCosts::default(),
+            normalization: GEDNormalization::Absolute,
+            matching_threshold: 2.0,
+        };
+        assert_eq!(algo.match_graphs(&g1, &g2), MatchingResult::ExactMatch);
+        // Substitute vertex label 3 -> 4, delete edge 0 -> 2, insert edge 2 -> 0
+        assert_eq!(algo.calc_distance(&g1, &g3), f64::INFINITY);
+        assert_eq!(algo.match_graphs(&g1, &g3), MatchingResult::NoMatch);
+
+        let algo = AlgoGraphMatching::GEDExact {
+            edit_costs: GEDEditCosts::default(),
+            normalization: GEDNormalization::Absolute,
+            matching_threshold: 3.0,
+        };
+        assert_eq!(algo.calc_distance(&g1, &g3), 3.0);
+        assert_eq!(algo.match_graphs(&g1, &g3), MatchingResult::RelaxedMatch);
+    }
+
+    #[test]
+    fn test_normalized_ged_matching() {
+        // 3 vertices and 2 edges each
+        let mut g1 = Graph::new(1);
+        g1.create_vertex_with_data(1, 2);
+        g1.create_vertex_with_data(2, 3);
+        g1.create_vertex_with_data(3, 3);
+        g1.vertices.get_mut(0).unwrap().push(1, 5);
+        g1.vertices.get_mut(0).unwrap().push(2, 5);
+
+        let mut g2 = Graph::new(2);
+        g2.create_vertex_with_data(1, 2);
+        g2.create_vertex_with_data(2, 3);
+        g2.create_vertex_with_data(4, 3);
+        g2.vertices.get_mut(0).unwrap().push(1, 5);
+        g2.vertices.get_mut(0).unwrap().push(2, 5);
+
+        for (normalization, expected) in [
+            (GEDNormalization::Absolute, 1.0),
+            (GEDNormalization::MaxEditCost, 0.1),
+            (GEDNormalization::GraphSize, 0.2),
+        ] {
+            let fast = AlgoGraphMatching::GEDFastHungarian {
+                edit_costs: GEDEditCosts::default(),
+                normalization,
+                matching_threshold: 0.2,
+            };
+            let exact = AlgoGraphMatching::GEDExact {
+                edit_costs: GEDEditCosts::default(),
+                normalization,
+                matching_threshold: 0.2,
+            };
+            assert_eq!(fast.calc_distance(&g1, &g1), 0.0);
+            assert_eq!(fast.match_graphs(&g1, &g1), MatchingResult::ExactMatch);
+            assert_eq!(fast.calc_distance(&g1, &g2), expected);
+            let expected_result = if normalization == GEDNormalization::Absolute {
+                // The exact search stops above the threshold
+                assert_eq!(exact.calc_distance(&g1, &g2), f64::INFINITY);
+                MatchingResult::NoMatch
+            } else {
+                assert_eq!(exact.calc_distance(&g1, &g2), expected);
+                MatchingResult::RelaxedMatch
+            };
+            assert_eq!(fast.match_graphs(&g1, &g2), expected_result);
+            assert_eq!(exact.match_graphs(&g1, &g2), expected_result);
+        }
+    }
 }
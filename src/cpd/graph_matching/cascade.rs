@@ -0,0 +1,272 @@
+use super::{
+    AlgoGraphMatching, GEDEditCosts, MatchingResult, graph_vf2_isomorphism, label_lower_bound,
+};
+use crate::data::graph::Graph;
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// One stage of the `AlgoGraphMatching::Cascade` matcher.
+///
+/// Every stage either rejects a pair (`NoMatch`) or passes it on to the next stage. Exact matches
+/// are only confirmed by VF2 after the last stage, unless a VF2 stage has already confirmed them.
+#[derive(Debug)]
+pub enum CascadeStage {
+    /// Rejects pairs whose `label_lower_bound` of the GED with the `edit_costs` exceeds
+    /// `max_label_difference`; with unit costs, the number of differing elements of the vertex
+    /// (label, type) multisets and edge label multisets.
+    LabelBound {
+        max_label_difference: usize,
+        edit_costs: GEDEditCosts,
+    },
+
+    /// Rejects pairs that the matcher does not consider a (relaxed) match, e.g. cosine
+    /// similarity or GED.
+    Matcher(AlgoGraphMatching),
+}
+
+/// Result of a stage without the VF2 confirmation of exact matches
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(super) enum FilterResult {
+    /// The graphs may be identical
+    Identical,
+    /// The graphs are similar, but not identical
+    Similar,
+    /// The graphs do not meet the threshold
+    Rejected,
+}
+
+/// Number of pairs resolved by each stage of a `AlgoGraphMatching::Cascade`.
+///
+/// The counters are atomic, since candidate pairs are matched in parallel.
+#[derive(Debug, Default)]
+pub struct CascadeStats {
+    /// Pairs rejected by the stage with the same index
+    pub rejected: Vec<AtomicUsize>,
+    /// Pairs that passed all stages, but cannot be identical
+    pub relaxed: AtomicUsize,
+    /// Pairs that passed all stages and were confirmed as exact matches by VF2
+    pub vf2_exact: AtomicUsize,
+    /// Pairs that passed all stages, but were not isomorphic
+    pub vf2_relaxed: AtomicUsize,
+}
+
+impl CascadeStats {
+    pub fn new(number_of_stages: usize) -> Self {
+        Self {
+            rejected: (0..number_of_stages).map(|_| AtomicUsize::new(0)).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for CascadeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i_stage, rejected) in self.rejected.iter().enumerate() {
+            writeln!(
+                f,
+                " - Stage {}: rejected   : {}",
+                i_stage + 1,
+                rejected.load(Ordering::Relaxed)
+            )?;
+        }
+        writeln!(
+            f,
+            " - Relaxed (no VF2)     : {}",
+            self.relaxed.load(Ordering::Relaxed)
+        )?;
+        writeln!(
+            f,
+            " - VF2 exact            : {}",
+            self.vf2_exact.load(Ordering::Relaxed)
+        )?;
+        write!(
+            f,
+            " - VF2 relaxed          : {}",
+            self.vf2_relaxed.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// Runs all stages; returns the index of the rejecting stage (`Err`) or whether the graphs may
+/// be identical (`Ok`).
+pub(super) fn run_stages(
+    stages: &[CascadeStage],
+    one_graph: &Graph,
+    other_graph: &Graph,
+) -> Result<bool, usize> {
+    let mut maybe_identical = true;
+    for (i_stage, stage) in stages.iter().enumerate() {
+        let result = match stage {
+            CascadeStage::LabelBound {
+                max_label_difference,
+                edit_costs,
+            } => match label_lower_bound(one_graph, other_graph, edit_costs) {
+                0 => FilterResult::Identical,
+                difference if difference <= *max_label_difference => FilterResult::Similar,
+                _ => FilterResult::Rejected,
+            },
+            CascadeStage::Matcher(algo_graph_matching) => {
+                algo_graph_matching.filter_graphs(one_graph, other_graph)
+            }
+        };
+        match result {
+            FilterResult::Identical => {}
+            FilterResult::Similar => maybe_identical = false,
+            FilterResult::Rejected => return Err(i_stage),
+        }
+    }
+    Ok(maybe_identical)
+}
+
+/// Matches two graphs with all stages and counts the deciding stage in `stats`, if any
+pub(super) fn match_cascade(
+    stages: &[CascadeStage],
+    stats: Option<&CascadeStats>,
+    one_graph: &Graph,
+    other_graph: &Graph,
+) -> MatchingResult {
+    let count = |counter: fn(&CascadeStats) -> &AtomicUsize| {
+        if let Some(stats) = stats {
+            counter(stats).fetch_add(1, Ordering::Relaxed);
+        }
+    };
+    match run_stages(stages, one_graph, other_graph) {
+        Err(i_stage) => {
+            if let Some(stats) = stats {
+                stats.rejected[i_stage].fetch_add(1, Ordering::Relaxed);
+            }
+            MatchingResult::NoMatch
+        }
+        Ok(false) => {
+            count(|stats| &stats.relaxed);
+            MatchingResult::RelaxedMatch
+        }
+        Ok(true) => {
+            // A VF2 stage only passes isomorphic graphs as identical
+            let confirmed = stages.iter().any(|stage| {
+                matches!(
+                    stage,
+                    CascadeStage::Matcher(AlgoGraphMatching::VF2IsomorphismTest)
+                )
+            });
+            if confirmed || graph_vf2_isomorphism(one_graph, other_graph) == 1.0 {
+                count(|stats| &stats.vf2_exact);
+                MatchingResult::ExactMatch
+            } else {
+                count(|stats| &stats.vf2_relaxed);
+                MatchingResult::RelaxedMatch
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpd::graph_matching::SimilarityMeasure;
+    use crate::cpd::test_utils::make_graph;
+
+    #[test]
+    fn test_cascade_stages_and_stats() {
+        let g1 = make_graph(&[(1, 2), (2, 3), (3, 3)], &[(0, 1, 5), (0, 2, 5)]);
+        // Same labels, different structure
+        let g2 = make_graph(&[(1, 2), (2, 3), (3, 3)], &[(0, 1, 5), (2, 0, 5)]);
+        // Isomorphic to g1
+        let g3 = make_graph(&[(3, 3), (1, 2), (2, 3)], &[(1, 0, 5), (1, 2, 5)]);
+        // Completely different labels
+        let g4 = make_graph(&[(7, 2), (8, 3)], &[(0, 1, 9)]);
+
+        let algo = AlgoGraphMatching::Cascade {
+            stages: vec![
+                CascadeStage::LabelBound {
+                    max_label_difference: 2,
+                    edit_costs: GEDEditCosts::default(),
+                },
+                CascadeStage::Matcher(AlgoGraphMatching::GEDFastHungarian {
+                    edit_costs: GEDEditCosts::default(),
+                    normalization: Default::default(),
+                    matching_threshold: 1.0,
+                }),
+            ],
+            stats: CascadeStats::new(2),
+        };
+        assert_eq!(algo.match_graphs(&g1, &g4), MatchingResult::NoMatch);
+        assert_eq!(algo.match_graphs(&g1, &g3), MatchingResult::ExactMatch);
+        // Reversing an edge costs a deletion and an insertion
+        assert_eq!(algo.match_graphs(&g1, &g2), MatchingResult::NoMatch);
+
+        let AlgoGraphMatching::Cascade { stats, .. } = &algo else {
+            unreachable!()
+        };
+        assert_eq!(stats.rejected[0].load(Ordering::Relaxed), 1);
+        assert_eq!(stats.rejected[1].load(Ordering::Relaxed), 1);
+        assert_eq!(stats.vf2_exact.load(Ordering::Relaxed), 1);
+
+        // Pairs compared for the exact match classes are not counted
+        assert_eq!(
+            algo.match_graphs_uncounted(&g1, &g3),
+            MatchingResult::ExactMatch
+        );
+        assert_eq!(stats.vf2_exact.load(Ordering::Relaxed), 1);
+
+        // A VF2 stage confirms the exact matches itself
+        let algo = AlgoGraphMatching::Cascade {
+            stages: vec![CascadeStage::Matcher(AlgoGraphMatching::VF2IsomorphismTest)],
+            stats: CascadeStats::new(1),
+        };
+        assert_eq!(algo.match_graphs(&g1, &g3), MatchingResult::ExactMatch);
+        assert_eq!(algo.match_graphs(&g1, &g2), MatchingResult::NoMatch);
+        let AlgoGraphMatching::Cascade { stats, .. } = &algo else {
+            unreachable!()
+        };
+        assert_eq!(stats.rejected[0].load(Ordering::Relaxed), 1);
+        assert_eq!(stats.vf2_exact.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.vf2_relaxed.load(Ordering::Relaxed), 0);
+
+        // The label lower bound of g1 and g4 is 5 with unit costs, but 7 if deleting a vertex
+        // costs 3
+        let expensive = GEDEditCosts {
+            node_del: 3,
+            node_ins: 3,
+            ..Default::default()
+        };
+        let stage = |edit_costs| AlgoGraphMatching::Cascade {
+            stages: vec![CascadeStage::LabelBound {
+                max_label_difference: 5,
+                edit_costs,
+            }],
+            stats: CascadeStats::new(1),
+        };
+        assert_eq!(
+            stage(GEDEditCosts::default()).match_graphs(&g1, &g4),
+            MatchingResult::RelaxedMatch
+        );
+        assert_eq!(
+            stage(expensive).match_graphs(&g1, &g4),
+            MatchingResult::NoMatch
+        );
+
+        let algo = AlgoGraphMatching::Cascade {
+            stages: vec![CascadeStage::Matcher(AlgoGraphMatching::CosineSimilarity {
+                measure: SimilarityMeasure::Cosine,
+                alpha: 0.5,
+                matching_threshold: 0.7,
+                idf: None,
+                label_similarity: None,
+            })],
+            stats: CascadeStats::new(1),
+        };
+        assert_eq!(algo.match_graphs(&g1, &g2), MatchingResult::RelaxedMatch);
+        // Same vertex and edge vectors, only VF2 detects the difference
+        let h1 = make_graph(&[(1, 2), (1, 2), (1, 2), (1, 2)], &[(0, 1, 0), (2, 3, 0)]);
+        let h2 = make_graph(&[(1, 2), (1, 2), (1, 2), (1, 2)], &[(0, 1, 0), (1, 2, 0)]);
+        assert_eq!(algo.match_graphs(&h1, &h2), MatchingResult::RelaxedMatch);
+        let AlgoGraphMatching::Cascade { stats, .. } = &algo else {
+            unreachable!()
+        };
+        assert_eq!(stats.relaxed.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.vf2_relaxed.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,109 @@
+use super::{AlgoGraphMatching, FilterResult, graph_vf2_isomorphism};
+use crate::data::graph::Graph;
+
+/// Rejects graphs with different activity cores, then classifies the object contexts of the
+/// graphs with the `context_matching`. The equal activity cores are left out, so they neither
+/// inflate the similarity nor the normalizer of the context.
+pub(super) fn filter_context_relaxed(
+    activity_vertex_types: &[usize],
+    context_matching: &AlgoGraphMatching,
+    one_graph: &Graph,
+    other_graph: &Graph,
+) -> FilterResult {
+    let one_core = one_graph.get_activity_core(activity_vertex_types);
+    let other_core = other_graph.get_activity_core(activity_vertex_types);
+    if one_core.get_vertex_vector() != other_core.get_vertex_vector()
+        || one_core.get_edge_vector() != other_core.get_edge_vector()
+        || graph_vf2_isomorphism(&one_core, &other_core) != 1.0
+    {
+        return FilterResult::Rejected;
+    }
+    context_matching.filter_graphs(
+        &one_graph.get_object_context(activity_vertex_types),
+        &other_graph.get_object_context(activity_vertex_types),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpd::graph_matching::{GEDEditCosts, GEDNormalization, MatchingResult};
+    use crate::cpd::test_utils::make_graph;
+
+    #[test]
+    fn test_context_relaxed_matching() {
+        // Activities (type 1) 1 -> 2, both connected to an object (type 2)
+        let g1 = make_graph(
+            &[(1, 1), (5, 2), (2, 1)],
+            &[(0, 2, 0), (0, 1, 3), (2, 1, 3)],
+        );
+        let core = g1.get_activity_core(&[1]);
+        assert_eq!(core.vertices.len(), 2);
+        assert_eq!(core.get_edge_vector().get(&(1, 1, 0, 2, 1)), Some(&1));
+        assert!(std::sync::Arc::ptr_eq(&core, &g1.get_activity_core(&[1])));
+
+        // Same collaboration, another object
+        let g2 = make_graph(
+            &[(1, 1), (2, 1), (6, 2)],
+            &[(0, 1, 0), (0, 2, 3), (1, 2, 3)],
+        );
+        // Reversed collaboration, same object
+        let g3 = make_graph(
+            &[(1, 1), (5, 2), (2, 1)],
+            &[(2, 0, 0), (0, 1, 3), (2, 1, 3)],
+        );
+        // Same collaboration, no context
+        let g4 = make_graph(&[(1, 1), (2, 1)], &[(0, 1, 0)]);
+
+        let algo = AlgoGraphMatching::ContextRelaxed {
+            activity_vertex_types: vec![1],
+            context_matching: Box::new(AlgoGraphMatching::GEDFastHungarian {
+                edit_costs: GEDEditCosts::default(),
+                normalization: Default::default(),
+                matching_threshold: 1.0,
+            }),
+        };
+        assert_eq!(algo.match_graphs(&g1, &g2), MatchingResult::RelaxedMatch);
+        assert_eq!(algo.match_graphs(&g1, &g1), MatchingResult::ExactMatch);
+        // Only two edits (an edge reversal) away, but the activity cores differ
+        assert_eq!(algo.match_graphs(&g1, &g3), MatchingResult::NoMatch);
+        // The activity cores are the same, but the contexts differ too much
+        assert_eq!(algo.match_graphs(&g1, &g4), MatchingResult::NoMatch);
+        assert_eq!(algo.calc_distance(&g1, &g2), 1.0);
+        assert_eq!(algo.calc_distance(&g1, &g3), 0.0);
+    }
+
+    #[test]
+    fn test_shared_core_does_not_hide_context() {
+        // Ten activities (type 1) in a chain with one object (type 2) at either end
+        let with_object = |object: (usize, usize), edge: (usize, usize, usize)| {
+            let mut vertices: Vec<(usize, usize)> = (1..=10).map(|label| (label, 1)).collect();
+            vertices.push(object);
+            let mut edges: Vec<(usize, usize, usize)> = (0..9).map(|i| (i, i + 1, 0)).collect();
+            edges.push(edge);
+            make_graph(&vertices, &edges)
+        };
+        let g1 = with_object((50, 2), (0, 10, 3));
+        let g2 = with_object((60, 2), (9, 10, 4));
+        let context = g1.get_object_context(&[1]);
+        assert_eq!(context.vertices.len(), 2);
+        assert_eq!(context.get_edge_vector().get(&(1, 1, 3, 50, 2)), Some(&1));
+
+        let context_matching = AlgoGraphMatching::GEDFastHungarian {
+            edit_costs: GEDEditCosts::default(),
+            normalization: GEDNormalization::GraphSize,
+            matching_threshold: 0.5,
+        };
+        // The whole graphs are within the threshold, although no part of the contexts is shared
+        assert_eq!(
+            context_matching.match_graphs(&g1, &g2),
+            MatchingResult::RelaxedMatch
+        );
+        let algo = AlgoGraphMatching::ContextRelaxed {
+            activity_vertex_types: vec![1],
+            context_matching: Box::new(context_matching),
+        };
+        assert_eq!(algo.match_graphs(&g1, &g2), MatchingResult::NoMatch);
+        assert!(algo.invariants_within_threshold(g1.get_invariants(), g2.get_invariants()));
+    }
+}
@@ -0,0 +1,1097 @@
+use super::LabelSimilarity;
+use crate::data::graph::{Graph, GraphInvariants};
+use petgraph::{
+    Direction,
+    graph::{DiGraph, NodeIndex},
+    visit::EdgeRef,
+};
+use std::{collections::HashMap, fmt, fs, path::Path, sync::Arc};
+
+/// Cost of substituting a node by a node of another vertex type in the Graph Edit Distance (GED)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TypeChangeCost {
+    /// Same costs as for any other substitution
+    #[default]
+    NodeSubstitution,
+    /// Nodes can only be substituted by nodes of the same vertex type, e.g. an activity is
+    /// never mapped onto an object
+    Forbidden,
+    /// Fixed cost for every substitution across vertex types
+    Cost(usize),
+}
+
+/// Normalization of the Graph Edit Distance (GED) for a relative matching threshold
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GEDNormalization {
+    /// The GED as is
+    #[default]
+    Absolute,
+    /// GED divided by the cost of deleting one graph and inserting the other (0.0 - 1.0)
+    MaxEditCost,
+    /// GED divided by the larger number of vertices plus edges of both graphs
+    GraphSize,
+}
+
+impl GEDNormalization {
+    /// Divisor for the GED of both graphs; 1.0 for `Absolute`
+    pub fn normalizer(
+        &self,
+        one_graph: &Graph,
+        other_graph: &Graph,
+        edit_costs: &GEDEditCosts,
+    ) -> f64 {
+        if *self == GEDNormalization::Absolute {
+            return 1.0;
+        }
+        self.size_normalizer(graph_size(one_graph), graph_size(other_graph), edit_costs)
+    }
+
+    /// Divisor for the GED of two graphs with the (number of vertices, number of edges)
+    /// `one_size` and `other_size`
+    fn size_normalizer(
+        &self,
+        one_size: (usize, usize),
+        other_size: (usize, usize),
+        edit_costs: &GEDEditCosts,
+    ) -> f64 {
+        let normalizer = match self {
+            GEDNormalization::Absolute => 1,
+            GEDNormalization::MaxEditCost => {
+                one_size.0 * edit_costs.node_del
+                    + one_size.1 * edit_costs.edge_del
+                    + other_size.0 * edit_costs.node_ins
+                    + other_size.1 * edit_costs.edge_ins
+            }
+            GEDNormalization::GraphSize => {
+                (one_size.0 + one_size.1).max(other_size.0 + other_size.1)
+            }
+        };
+        // Only two empty graphs have a normalizer of 0, and their GED is 0 as well
+        normalizer.max(1) as f64
+    }
+}
+
+/// Costs for Graph Edit Distance (GED)
+///
+/// Besides the six basic edit costs, node substitutions can be priced per vertex type and per
+/// pair of vertex labels, e.g. to make substituting an activity label far more expensive than
+/// substituting a context object label. See `read_substitution_costs` for the file format.
+/// Substitutions across vertex types are controlled by `type_change`. With a `label_similarity`,
+/// substituting near-synonymous labels gets cheaper.
+#[derive(Clone, Debug)]
+pub struct GEDEditCosts {
+    pub node_sub: usize,
+    pub node_ins: usize,
+    pub node_del: usize,
+    pub edge_sub: usize,
+    pub edge_ins: usize,
+    pub edge_del: usize,
+    /// Substitution cost for two nodes of the same vertex type, replaces `node_sub`
+    pub vertex_type_sub: HashMap<usize, usize>,
+    /// Substitution cost for a pair of vertex labels (in any order), replaces `node_sub` and
+    /// `vertex_type_sub`
+    pub label_pair_sub: HashMap<(usize, usize), usize>,
+    /// Cost of substituting a node by a node of another vertex type, takes precedence over all
+    /// other substitution costs
+    pub type_change: TypeChangeCost,
+    /// Scales the substitution cost of two labels without an entry in `label_pair_sub` by
+    /// `1 - similarity` (rounded)
+    pub label_similarity: Option<Arc<LabelSimilarity>>,
+}
+
+impl Default for GEDEditCosts {
+    fn default() -> Self {
+        Self {
+            node_sub: 1,
+            node_ins: 1,
+            node_del: 1,
+            edge_sub: 1,
+            edge_ins: 1,
+            edge_del: 1,
+            vertex_type_sub: HashMap::new(),
+            label_pair_sub: HashMap::new(),
+            type_change: TypeChangeCost::NodeSubstitution,
+            label_similarity: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct EditCostsParseError {
+    message: String,
+}
+
+impl fmt::Display for EditCostsParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl GEDEditCosts {
+    /// Cost to substitute a node with the (label, type) `one` by a node with the (label, type)
+    /// `other`; 0 if both are equal and `None` if the substitution is forbidden.
+    ///
+    /// The `type_change` cost takes precedence over a cost for the label pair, which takes
+    /// precedence over a cost for the vertex type, which takes precedence over `node_sub`. The
+    /// latter two are scaled by the `label_similarity`.
+    pub fn node_substitution_cost(
+        &self,
+        one: &(usize, usize),
+        other: &(usize, usize),
+    ) -> Option<usize> {
+        if one == other {
+            return Some(0);
+        }
+        if one.1 != other.1 {
+            match self.type_change {
+                TypeChangeCost::NodeSubstitution => {}
+                TypeChangeCost::Forbidden => return None,
+                TypeChangeCost::Cost(cost) => return Some(cost),
+            }
+        }
+        if let Some(cost) = self
+            .label_pair_sub
+            .get(&(one.0, other.0))
+            .or_else(|| self.label_pair_sub.get(&(other.0, one.0)))
+        {
+            return Some(*cost);
+        }
+        let cost = if one.1 == other.1
+            && let Some(cost) = self.vertex_type_sub.get(&one.1)
+        {
+            *cost
+        } else {
+            self.node_sub
+        };
+        match &self.label_similarity {
+            Some(label_similarity) => Some(
+                (cost as f64 * (1.0 - label_similarity.similarity(one.0, other.0))).round()
+                    as usize,
+            ),
+            None => Some(cost),
+        }
+    }
+
+    /// Lowest cost to substitute two nodes with different (label, type); deleting one and
+    /// inserting the other is an alternative to a substitution.
+    pub fn min_node_substitution_cost(&self) -> usize {
+        let type_change_cost = match self.type_change {
+            TypeChangeCost::Cost(cost) => Some(cost),
+            _ => None,
+        };
+        let min_type_cost = self
+            .vertex_type_sub
+            .values()
+            .fold(self.node_sub, |min, cost| min.min(*cost));
+        let similar_labels_cost = self.label_similarity.as_ref().map(|label_similarity| {
+            (min_type_cost as f64 * (1.0 - label_similarity.max_similarity())).round() as usize
+        });
+        self.label_pair_sub
+            .values()
+            .chain(type_change_cost.iter())
+            .chain(similar_labels_cost.iter())
+            .fold(min_type_cost, |min, cost| min.min(*cost))
+            .min(self.node_del + self.node_ins)
+    }
+
+    /// Reads node substitution cost tables from a plain text file:
+    ///
+    /// - t-line: substitution cost for two nodes of the same vertex type
+    ///     - Format `t t c`: vertex type `t`, cost `c`
+    /// - l-line: substitution cost for a pair of vertex labels (symmetric)
+    ///     - Format `l l1 l2 c`: vertex labels `l1` and `l2`, cost `c`
+    ///
+    /// Empty lines and lines starting with `#` are ignored. The entries are added to the tables
+    /// of `self`.
+    pub fn read_substitution_costs<P>(&mut self, path: P) -> Result<(), EditCostsParseError>
+    where
+        P: AsRef<Path>,
+    {
+        let content = fs::read_to_string(path).map_err(|_| EditCostsParseError {
+            message: "Error reading file".to_string(),
+        })?;
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut data = line.split_whitespace();
+            let line_type = data.next().unwrap();
+            let values = data
+                .map(|value| value.parse::<usize>())
+                .collect::<Result<Vec<usize>, _>>()
+                .map_err(|_| EditCostsParseError {
+                    message: format!("Line {}, invalid number", line_number + 1),
+                })?;
+            match (line_type, values.as_slice()) {
+                ("t", [vertex_type, cost]) => {
+                    self.vertex_type_sub.insert(*vertex_type, *cost);
+                }
+                ("l", [label, other_label, cost]) => {
+                    self.label_pair_sub.insert((*label, *other_label), *cost);
+                }
+                ("t", _) | ("l", _) => {
+                    return Err(EditCostsParseError {
+                        message: format!(
+                            "Line {}, wrong number of values for '{}'",
+                            line_number + 1,
+                            line_type
+                        ),
+                    });
+                }
+                _ => {
+                    return Err(EditCostsParseError {
+                        message: format!(
+                            "Line {}, unknown line type '{}'",
+                            line_number + 1,
+                            line_type
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Cost to turn the edge labels `one` into the edge labels `other`, where both are sorted
+    /// multisets of the parallel edges between two nodes.
+    ///
+    /// Equal labels are kept for free, the remaining edges are substituted pairwise (or deleted and
+    /// inserted if that is cheaper), and the surplus is deleted or inserted.
+    pub fn edge_labels_cost(&self, one: &[usize], other: &[usize]) -> usize {
+        multiset_edit_cost(
+            one.len(),
+            other.len(),
+            sorted_multiset_intersection(one, other),
+            self.edge_sub.min(self.edge_del + self.edge_ins),
+            self.edge_del,
+            self.edge_ins,
+        )
+    }
+}
+
+/// Cost to turn a multiset of size `one_size` into a multiset of size `other_size` with `common`
+/// equal elements: the remaining elements are substituted pairwise and the surplus is deleted or
+/// inserted.
+fn multiset_edit_cost(
+    one_size: usize,
+    other_size: usize,
+    common: usize,
+    sub: usize,
+    del: usize,
+    ins: usize,
+) -> usize {
+    (one_size.min(other_size) - common) * sub
+        + one_size.saturating_sub(other_size) * del
+        + other_size.saturating_sub(one_size) * ins
+}
+
+/// Sizes of two multisets and the size of their intersection
+fn multiset_sizes_and_common<T: Eq + std::hash::Hash>(
+    one: &HashMap<T, usize>,
+    other: &HashMap<T, usize>,
+) -> (usize, usize, usize) {
+    let common = one
+        .iter()
+        .map(|(key, count)| (*count).min(*other.get(key).unwrap_or(&0)))
+        .sum();
+    (one.values().sum(), other.values().sum(), common)
+}
+
+/// Number of vertices and edges of a graph
+fn graph_size(graph: &Graph) -> (usize, usize) {
+    let edges = graph.vertices.iter().map(|vertex| vertex.edges.len()).sum();
+    (graph.vertices.len(), edges)
+}
+
+/// Lower bound of the GED from the number of vertices and edges of both graphs: the surplus
+/// has to be deleted or inserted.
+///
+/// Cheaper to compute than `label_lower_bound`, but never larger.
+pub fn size_lower_bound(
+    one_graph: &Graph,
+    other_graph: &Graph,
+    edit_costs: &GEDEditCosts,
+) -> usize {
+    sizes_lower_bound(graph_size(one_graph), graph_size(other_graph), edit_costs)
+}
+
+/// `size_lower_bound` of two graphs with the (number of vertices, number of edges) `one_size`
+/// and `other_size`
+fn sizes_lower_bound(
+    (one_vertices, one_edges): (usize, usize),
+    (other_vertices, other_edges): (usize, usize),
+    edit_costs: &GEDEditCosts,
+) -> usize {
+    let common_vertices = one_vertices.min(other_vertices);
+    let common_edges = one_edges.min(other_edges);
+    multiset_edit_cost(
+        one_vertices,
+        other_vertices,
+        common_vertices,
+        0,
+        edit_costs.node_del,
+        edit_costs.node_ins,
+    ) + multiset_edit_cost(
+        one_edges,
+        other_edges,
+        common_edges,
+        0,
+        edit_costs.edge_del,
+        edit_costs.edge_ins,
+    )
+}
+
+/// Whether the `size_lower_bound` of two graphs with the invariants `one` and `other` is within
+/// the (normalized) `matching_threshold`
+pub(super) fn size_bound_within_threshold(
+    one: &GraphInvariants,
+    other: &GraphInvariants,
+    edit_costs: &GEDEditCosts,
+    normalization: &GEDNormalization,
+    matching_threshold: f64,
+) -> bool {
+    let one_size = (one.number_of_vertices, one.number_of_edges);
+    let other_size = (other.number_of_vertices, other.number_of_edges);
+    let normalizer = normalization.size_normalizer(one_size, other_size, edit_costs);
+    sizes_lower_bound(one_size, other_size, edit_costs) as f64 / normalizer <= matching_threshold
+}
+
+/// Lower bound of the GED from the (label, type) multisets of the vertices (`get_vertex_vector`)
+/// and the label multisets of the edges (`get_edge_vector`).
+///
+/// Vertices and edges without an equal counterpart have to be substituted, deleted, or inserted,
+/// regardless of the structure of both graphs. With the default edit costs, the bound is the
+/// number of differing vertex and edge labels.
+pub fn label_lower_bound(
+    one_graph: &Graph,
+    other_graph: &Graph,
+    edit_costs: &GEDEditCosts,
+) -> usize {
+    let edge_labels = |graph: &Graph| {
+        let mut edge_labels: HashMap<usize, usize> = HashMap::new();
+        for (key, count) in graph.get_edge_vector().iter() {
+            *edge_labels.entry(key.2).or_default() += count;
+        }
+        edge_labels
+    };
+    let (one_vertices, other_vertices, common_vertices) = multiset_sizes_and_common(
+        &one_graph.get_vertex_vector(),
+        &other_graph.get_vertex_vector(),
+    );
+    let (one_edges, other_edges, common_edges) =
+        multiset_sizes_and_common(&edge_labels(one_graph), &edge_labels(other_graph));
+    multiset_edit_cost(
+        one_vertices,
+        other_vertices,
+        common_vertices,
+        edit_costs.min_node_substitution_cost(),
+        edit_costs.node_del,
+        edit_costs.node_ins,
+    ) + multiset_edit_cost(
+        one_edges,
+        other_edges,
+        common_edges,
+        edit_costs
+            .edge_sub
+            .min(edit_costs.edge_del + edit_costs.edge_ins),
+        edit_costs.edge_del,
+        edit_costs.edge_ins,
+    )
+}
+
+/// Number of common elements of two sorted multisets
+fn sorted_multiset_intersection<T: Ord>(one: &[T], other: &[T]) -> usize {
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < one.len() && j < other.len() {
+        match one[i].cmp(&other[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    common
+}
+
+/// Sorted labels of the edges incident to a node in one direction
+fn incident_edge_labels(
+    g: &DiGraph<(usize, usize), usize>,
+    node: NodeIndex,
+    direction: Direction,
+) -> Vec<usize> {
+    let mut labels: Vec<usize> = g
+        .edges_directed(node, direction)
+        .map(|e| *e.weight())
+        .collect();
+    labels.sort_unstable();
+    labels
+}
+
+/// Sorted labels of the (parallel) edges per (source, target) pair of nodes
+pub(super) fn edge_labels_by_node_pair(
+    g: &DiGraph<(usize, usize), usize>,
+) -> HashMap<(usize, usize), Vec<usize>> {
+    let mut edge_labels: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for e in g.edge_references() {
+        edge_labels
+            .entry((e.source().index(), e.target().index()))
+            .or_default()
+            .push(*e.weight());
+    }
+    edge_labels
+        .values_mut()
+        .for_each(|labels| labels.sort_unstable());
+    edge_labels
+}
+
+/// Computes the cost of the edit path induced by a node assignment.
+///
+/// `assignment[i]` is the node of `g2` that node `i` of `g1` is substituted by, or `None` if node
+/// `i` is deleted; all nodes of `g2` that are not assigned are inserted. Edges between two
+/// substituted nodes are compared with the edges between their images; all other edges of `g1`
+/// are deleted and all other edges of `g2` are inserted.
+///
+/// Every assignment results in a valid edit path, so the cost is an upper bound of the exact GED;
+/// `None` if the assignment contains a forbidden substitution.
+pub(super) fn edit_path_cost(
+    g1: &DiGraph<(usize, usize), usize>,
+    g2: &DiGraph<(usize, usize), usize>,
+    assignment: &[Option<usize>],
+    edit_costs: &GEDEditCosts,
+) -> Option<usize> {
+    let mut cost = 0;
+    let mut is_assigned = vec![false; g2.node_count()];
+    for (i, maybe_j) in assignment.iter().enumerate() {
+        match maybe_j {
+            Some(j) => {
+                is_assigned[*j] = true;
+                cost += edit_costs
+                    .node_substitution_cost(&g1[NodeIndex::new(i)], &g2[NodeIndex::new(*j)])?;
+            }
+            None => cost += edit_costs.node_del,
+        }
+    }
+    cost += is_assigned.iter().filter(|a| !**a).count() * edit_costs.node_ins;
+
+    let g1_edges = edge_labels_by_node_pair(g1);
+    let mut g2_edges = edge_labels_by_node_pair(g2);
+    for ((from, to), labels) in g1_edges.iter() {
+        match (assignment[*from], assignment[*to]) {
+            (Some(from_2), Some(to_2)) => {
+                let labels_2 = g2_edges.remove(&(from_2, to_2)).unwrap_or_default();
+                cost += edit_costs.edge_labels_cost(labels, &labels_2);
+            }
+            _ => cost += labels.len() * edit_costs.edge_del,
+        }
+    }
+    cost += g2_edges.values().map(|labels| labels.len()).sum::<usize>() * edit_costs.edge_ins;
+    Some(cost)
+}
+
+/// Fast approximate graph edit distance using bipartite matching (Riesen & Bunke).
+///
+/// A square cost matrix of size `n1 + n2` describes the substitution of every node pair (upper
+/// left), the deletion of every node of `g1` (upper right diagonal) and the insertion of every
+/// node of `g2` (lower left diagonal). Each entry also contains the cost of the best local
+/// assignment of the incoming and outgoing edges of the nodes. Since every edge is seen at both of
+/// its end nodes, the edge costs count half (node costs are doubled to stay in integers).
+///
+/// The Hungarian algorithm finds the optimal node assignment for this matrix, and the returned
+/// distance is the exact cost of the edit path derived from it (see `edit_path_cost`). The result
+/// is therefore an upper bound of the exact GED.
+pub fn fast_ged(one_graph: &Graph, other_graph: &Graph, edit_costs: &GEDEditCosts) -> usize {
+    let g1 = one_graph.get_digraph();
+    let g2 = other_graph.get_digraph();
+    bipartite_assignment(&g1, &g2, edit_costs)
+        .and_then(|assignment| edit_path_cost(&g1, &g2, &assignment, edit_costs))
+        .unwrap_or(0)
+}
+
+/// Node assignment of the bipartite GED approximation; `None` if both graphs are empty.
+///
+/// Forbidden substitutions are never part of the assignment, since deleting and inserting all
+/// nodes is always cheaper.
+pub(super) fn bipartite_assignment(
+    g1: &DiGraph<(usize, usize), usize>,
+    g2: &DiGraph<(usize, usize), usize>,
+    edit_costs: &GEDEditCosts,
+) -> Option<Vec<Option<usize>>> {
+    let n1 = g1.node_count();
+    let n2 = g2.node_count();
+    let size = n1 + n2;
+    if size == 0 {
+        return None;
+    }
+    let edges_of = |g: &DiGraph<(usize, usize), usize>, i: usize| {
+        let node = NodeIndex::new(i);
+        (
+            incident_edge_labels(g, node, Direction::Outgoing),
+            incident_edge_labels(g, node, Direction::Incoming),
+        )
+    };
+    let g1_edges: Vec<_> = (0..n1).map(|i| edges_of(g1, i)).collect();
+    let g2_edges: Vec<_> = (0..n2).map(|j| edges_of(g2, j)).collect();
+
+    // `None` marks the forbidden entries
+    let mut cost_matrix: Vec<Option<usize>> = vec![None; size * size];
+    for i in 0..n1 {
+        let (out_1, in_1) = &g1_edges[i];
+        for j in 0..n2 {
+            let (out_2, in_2) = &g2_edges[j];
+            cost_matrix[i * size + j] = edit_costs
+                .node_substitution_cost(&g1[NodeIndex::new(i)], &g2[NodeIndex::new(j)])
+                .map(|node_cost| {
+                    2 * node_cost
+                        + edit_costs.edge_labels_cost(out_1, out_2)
+                        + edit_costs.edge_labels_cost(in_1, in_2)
+                });
+        }
+        // Deletion of node i
+        cost_matrix[i * size + n2 + i] =
+            Some(2 * edit_costs.node_del + (out_1.len() + in_1.len()) * edit_costs.edge_del);
+    }
+    for (j, (out_2, in_2)) in g2_edges.iter().enumerate() {
+        // Insertion of node j
+        cost_matrix[(n1 + j) * size + j] =
+            Some(2 * edit_costs.node_ins + (out_2.len() + in_2.len()) * edit_costs.edge_ins);
+        // Dummy to dummy
+        for k in 0..n1 {
+            cost_matrix[(n1 + j) * size + n2 + k] = Some(0);
+        }
+    }
+
+    // Any assignment using a forbidden entry is more expensive than every other assignment
+    let forbidden = cost_matrix.iter().flatten().sum::<usize>() + 1;
+    let cost_matrix: Vec<usize> = cost_matrix
+        .into_iter()
+        .map(|c| c.unwrap_or(forbidden))
+        .collect();
+
+    let assignment = hungarian::minimize(&cost_matrix, size, size);
+    Some(
+        assignment
+            .into_iter()
+            .take(n1)
+            .map(|maybe_j| maybe_j.filter(|j| *j < n2))
+            .collect(),
+    )
+}
+
+/// Exact graph edit distance using a depth-first branch and bound search.
+///
+/// The nodes of `g1` (the one with more edges first) are assigned one after another to an unused
+/// node of `g2` or deleted. Each step adds the node cost and the cost of the edges to the already
+/// assigned nodes; the remaining nodes are estimated by a label multiset lower bound. A branch is
+/// cut as soon as its cost plus the lower bound reaches the best edit path found so far. The
+/// search starts with the bipartite approximation as upper bound.
+///
+/// If `max_cost` is given, the search stops early once it is certain that the distance exceeds it
+/// and returns `None`.
+pub fn exact_ged(
+    one_graph: &Graph,
+    other_graph: &Graph,
+    edit_costs: &GEDEditCosts,
+    max_cost: Option<usize>,
+) -> Option<usize> {
+    let g1 = one_graph.get_digraph();
+    let g2 = other_graph.get_digraph();
+    let upper_bound = bipartite_assignment(&g1, &g2, edit_costs)
+        .and_then(|assignment| edit_path_cost(&g1, &g2, &assignment, edit_costs))
+        .unwrap_or(0);
+
+    let mut order: Vec<usize> = (0..g1.node_count()).collect();
+    order.sort_by_key(|i| {
+        std::cmp::Reverse(
+            g1.edges_directed(NodeIndex::new(*i), Direction::Outgoing)
+                .count()
+                + g1.edges_directed(NodeIndex::new(*i), Direction::Incoming)
+                    .count(),
+        )
+    });
+    let mut search = ExactGEDSearch {
+        g1: &g1,
+        g2: &g2,
+        g1_edges: edge_labels_by_node_pair(&g1),
+        g2_edges: edge_labels_by_node_pair(&g2),
+        edit_costs,
+        order,
+        assignment: vec![None; g1.node_count()],
+        is_used: vec![false; g2.node_count()],
+        // Only edit paths cheaper than `best` are of interest
+        best: match max_cost {
+            Some(max_cost) if upper_bound > max_cost => max_cost + 1,
+            _ => upper_bound,
+        },
+        found: max_cost.is_none_or(|max_cost| upper_bound <= max_cost),
+    };
+    search.search(0, 0);
+    search.found.then_some(search.best)
+}
+
+struct ExactGEDSearch<'a> {
+    g1: &'a DiGraph<(usize, usize), usize>,
+    g2: &'a DiGraph<(usize, usize), usize>,
+    g1_edges: HashMap<(usize, usize), Vec<usize>>,
+    g2_edges: HashMap<(usize, usize), Vec<usize>>,
+    edit_costs: &'a GEDEditCosts,
+    order: Vec<usize>,
+    /// Assignment of the already processed nodes of g1 (`Some(None)` = deleted)
+    assignment: Vec<Option<Option<usize>>>,
+    is_used: Vec<bool>,
+    best: usize,
+    found: bool,
+}
+
+impl ExactGEDSearch<'_> {
+    fn search(&mut self, depth: usize, cost: usize) {
+        if depth == self.order.len() {
+            let cost = cost + self.insertion_cost();
+            if cost < self.best {
+                self.best = cost;
+                self.found = true;
+            }
+            return;
+        }
+        if cost + self.lower_bound(depth) >= self.best {
+            return;
+        }
+        let node = self.order[depth];
+        let mut options: Vec<(Option<usize>, usize)> = (0..self.g2.node_count())
+            .filter(|j| !self.is_used[*j])
+            .map(Some)
+            .chain(std::iter::once(None))
+            .filter_map(|option| Some((option, self.assignment_cost(node, option)?)))
+            .collect();
+        // Cheap options first to find good edit paths early
+        options.sort_by_key(|(_, option_cost)| *option_cost);
+        for (option, option_cost) in options {
+            if cost + option_cost >= self.best {
+                break;
+            }
+            self.assignment[node] = Some(option);
+            if let Some(j) = option {
+                self.is_used[j] = true;
+            }
+            self.search(depth + 1, cost + option_cost);
+            if let Some(j) = option {
+                self.is_used[j] = false;
+            }
+            self.assignment[node] = None;
+        }
+    }
+
+    /// Node cost plus the cost of the edges between `node` and the already assigned nodes; `None`
+    /// if the substitution is forbidden
+    fn assignment_cost(&self, node: usize, option: Option<usize>) -> Option<usize> {
+        let empty = Vec::new();
+        let g1_labels = |from: usize, to: usize| self.g1_edges.get(&(from, to)).unwrap_or(&empty);
+        let g2_labels = |from: usize, to: usize| self.g2_edges.get(&(from, to)).unwrap_or(&empty);
+        let mut cost = match option {
+            Some(j) => self.edit_costs.node_substitution_cost(
+                &self.g1[NodeIndex::new(node)],
+                &self.g2[NodeIndex::new(j)],
+            )?,
+            None => self.edit_costs.node_del,
+        };
+        // Self loops and edges in both directions to the already assigned nodes
+        let assigned = self
+            .assignment
+            .iter()
+            .enumerate()
+            .filter_map(|(other, a)| a.map(|a| (other, a)))
+            .chain(std::iter::once((node, option)));
+        for (other, other_option) in assigned {
+            let directions: &[(usize, usize)] = if other == node {
+                &[(node, node)]
+            } else {
+                &[(node, other), (other, node)]
+            };
+            for (from, to) in directions.iter() {
+                let labels = g1_labels(*from, *to);
+                cost += match (option, other_option) {
+                    (Some(j), Some(other_j)) => {
+                        let (from_2, to_2) = if *from == node {
+                            (j, other_j)
+                        } else {
+                            (other_j, j)
+                        };
+                        self.edit_costs
+                            .edge_labels_cost(labels, g2_labels(from_2, to_2))
+                    }
+                    _ => labels.len() * self.edit_costs.edge_del,
+                };
+            }
+        }
+        Some(cost)
+    }
+
+    /// Inserts all unused nodes of g2 and all edges with an unused end node
+    fn insertion_cost(&self) -> usize {
+        let nodes = self.is_used.iter().filter(|used| !**used).count() * self.edit_costs.node_ins;
+        let edges: usize = self
+            .g2_edges
+            .iter()
+            .filter(|((from, to), _)| !self.is_used[*from] || !self.is_used[*to])
+            .map(|(_, labels)| labels.len())
+            .sum();
+        nodes + edges * self.edit_costs.edge_ins
+    }
+
+    /// Lower bound of the node costs of the remaining nodes from their label multisets
+    fn lower_bound(&self, depth: usize) -> usize {
+        let mut remaining_1: Vec<(usize, usize)> = self.order[depth..]
+            .iter()
+            .map(|i| self.g1[NodeIndex::new(*i)])
+            .collect();
+        let mut remaining_2: Vec<(usize, usize)> = (0..self.g2.node_count())
+            .filter(|j| !self.is_used[*j])
+            .map(|j| self.g2[NodeIndex::new(j)])
+            .collect();
+        remaining_1.sort_unstable();
+        remaining_2.sort_unstable();
+        multiset_edit_cost(
+            remaining_1.len(),
+            remaining_2.len(),
+            sorted_multiset_intersection(&remaining_1, &remaining_2),
+            self.edit_costs.min_node_substitution_cost(),
+            self.edit_costs.node_del,
+            self.edit_costs.node_ins,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpd::test_utils::make_graph;
+    use itertools::Itertools;
+
+    /// Exact GED by trying every node assignment, only for tiny graphs
+    fn brute_force_ged(one_graph: &Graph, other_graph: &Graph, edit_costs: &GEDEditCosts) -> usize {
+        let g1 = one_graph.get_digraph();
+        let g2 = other_graph.get_digraph();
+        let n1 = g1.node_count();
+        let n2 = g2.node_count();
+        // Each node of g1 is assigned to a node of g2 or to one of n1 deletion slots
+        (0..n2 + n1)
+            .permutations(n1)
+            .filter_map(|perm| {
+                let assignment: Vec<Option<usize>> =
+                    perm.into_iter().map(|j| (j < n2).then_some(j)).collect();
+                edit_path_cost(&g1, &g2, &assignment, edit_costs)
+            })
+            .min()
+            .unwrap_or(n2 * edit_costs.node_ins + g2.edge_count() * edit_costs.edge_ins)
+    }
+
+    fn test_graphs() -> Vec<Graph> {
+        vec![
+            make_graph(&[], &[]),
+            make_graph(&[(1, 2)], &[(0, 0, 3)]),
+            make_graph(&[(1, 2), (2, 3)], &[(0, 1, 0), (1, 0, 0)]),
+            make_graph(&[(1, 2), (2, 3), (3, 4)], &[(0, 1, 5), (1, 2, 2)]),
+            make_graph(&[(1, 2), (2, 3), (3, 4)], &[(0, 2, 7), (1, 0, 3)]),
+            make_graph(
+                &[(1, 2), (2, 3), (3, 4)],
+                &[(1, 0, 5), (2, 1, 2), (2, 1, 2)],
+            ),
+            make_graph(
+                &[(1, 2), (1, 2), (3, 4), (5, 4)],
+                &[(0, 1, 1), (1, 0, 1), (0, 2, 1), (1, 3, 2), (3, 2, 1)],
+            ),
+            make_graph(
+                &[(1, 2), (3, 4), (1, 2), (6, 4)],
+                &[(0, 2, 1), (2, 0, 1), (0, 1, 1), (2, 3, 2)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_fast_ged_is_upper_bound() {
+        let cost_variants = [
+            GEDEditCosts::default(),
+            GEDEditCosts {
+                node_sub: 3,
+                node_ins: 2,
+                node_del: 1,
+                edge_sub: 1,
+                edge_ins: 4,
+                edge_del: 2,
+                ..Default::default()
+            },
+            GEDEditCosts {
+                node_sub: 1,
+                node_ins: 5,
+                node_del: 5,
+                edge_sub: 5,
+                edge_ins: 1,
+                edge_del: 1,
+                ..Default::default()
+            },
+            GEDEditCosts {
+                vertex_type_sub: HashMap::from([(2, 3), (4, 0)]),
+                label_pair_sub: HashMap::from([((1, 3), 2), ((5, 3), 0)]),
+                ..Default::default()
+            },
+            GEDEditCosts {
+                type_change: TypeChangeCost::Forbidden,
+                ..Default::default()
+            },
+            GEDEditCosts {
+                type_change: TypeChangeCost::Cost(0),
+                ..Default::default()
+            },
+        ];
+        let graphs = test_graphs();
+        for edit_costs in cost_variants.iter() {
+            for g1 in graphs.iter() {
+                for g2 in graphs.iter() {
+                    let approx = fast_ged(g1, g2, edit_costs);
+                    let exact = brute_force_ged(g1, g2, edit_costs);
+                    assert!(
+                        approx >= exact,
+                        "fast_ged {approx} < exact GED {exact} for {edit_costs:?}"
+                    );
+                }
+                assert_eq!(fast_ged(g1, g1, edit_costs), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_exact_ged_matches_brute_force() {
+        let edit_costs = GEDEditCosts {
+            node_sub: 2,
+            node_ins: 1,
+            node_del: 3,
+            edge_sub: 1,
+            edge_ins: 2,
+            edge_del: 1,
+            vertex_type_sub: HashMap::from([(4, 1), (2, 5)]),
+            label_pair_sub: HashMap::from([((3, 5), 0), ((6, 3), 1)]),
+            type_change: TypeChangeCost::NodeSubstitution,
+            label_similarity: None,
+        };
+        let forbidden_type_change = GEDEditCosts {
+            type_change: TypeChangeCost::Forbidden,
+            ..edit_costs.clone()
+        };
+        let expensive_type_change = GEDEditCosts {
+            type_change: TypeChangeCost::Cost(3),
+            ..Default::default()
+        };
+        let mut label_similarity = LabelSimilarity::default();
+        label_similarity.insert(1, 3, 0.5);
+        label_similarity.insert(2, 6, 0.9);
+        let similar_labels = GEDEditCosts {
+            node_sub: 4,
+            label_similarity: Some(Arc::new(label_similarity)),
+            ..Default::default()
+        };
+        let graphs = test_graphs();
+        for costs in [
+            GEDEditCosts::default(),
+            edit_costs,
+            forbidden_type_change,
+            expensive_type_change,
+            similar_labels,
+        ] {
+            for g1 in graphs.iter() {
+                for g2 in graphs.iter() {
+                    let exact = brute_force_ged(g1, g2, &costs);
+                    assert_eq!(exact_ged(g1, g2, &costs, None), Some(exact));
+                    assert!(exact <= fast_ged(g1, g2, &costs));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lower_bounds() {
+        let g1 = make_graph(&[(1, 2), (2, 3), (2, 3)], &[(0, 1, 5), (0, 2, 5)]);
+        let g2 = make_graph(&[(1, 2), (2, 3)], &[(0, 1, 5), (1, 0, 6)]);
+        let edit_costs = GEDEditCosts::default();
+        // One vertex (2, 3) and one edge label 5 vs. 6
+        assert_eq!(label_lower_bound(&g1, &g2, &edit_costs), 2);
+        assert_eq!(size_lower_bound(&g1, &g2, &edit_costs), 1);
+        assert_eq!(label_lower_bound(&g1, &g1, &edit_costs), 0);
+
+        let cost_variants = [
+            edit_costs,
+            GEDEditCosts {
+                node_sub: 3,
+                node_ins: 2,
+                node_del: 1,
+                edge_sub: 1,
+                edge_ins: 4,
+                edge_del: 2,
+                type_change: TypeChangeCost::Forbidden,
+                ..Default::default()
+            },
+            GEDEditCosts {
+                label_pair_sub: HashMap::from([((1, 3), 0)]),
+                ..Default::default()
+            },
+        ];
+        let graphs = test_graphs();
+        for edit_costs in cost_variants.iter() {
+            for g1 in graphs.iter() {
+                for g2 in graphs.iter() {
+                    let exact = brute_force_ged(g1, g2, edit_costs);
+                    let label_bound = label_lower_bound(g1, g2, edit_costs);
+                    assert!(
+                        label_bound <= exact,
+                        "{label_bound} > {exact} for {edit_costs:?}"
+                    );
+                    assert!(size_lower_bound(g1, g2, edit_costs) <= label_bound);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_exact_ged_stops_above_max_cost() {
+        let graphs = test_graphs();
+        let edit_costs = GEDEditCosts::default();
+        for g1 in graphs.iter() {
+            for g2 in graphs.iter() {
+                let exact = brute_force_ged(g1, g2, &edit_costs);
+                for max_cost in 0..6 {
+                    let expected = (exact <= max_cost).then_some(exact);
+                    assert_eq!(exact_ged(g1, g2, &edit_costs, Some(max_cost)), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_node_substitution_cost_tables() {
+        let edit_costs = GEDEditCosts {
+            node_sub: 2,
+            vertex_type_sub: HashMap::from([(1, 10)]),
+            label_pair_sub: HashMap::from([((3, 4), 1)]),
+            ..Default::default()
+        };
+        assert_eq!(edit_costs.node_substitution_cost(&(3, 1), &(3, 1)), Some(0));
+        assert_eq!(
+            edit_costs.node_substitution_cost(&(3, 1), &(5, 1)),
+            Some(10)
+        );
+        assert_eq!(edit_costs.node_substitution_cost(&(3, 2), &(5, 2)), Some(2));
+        assert_eq!(edit_costs.node_substitution_cost(&(3, 1), &(4, 1)), Some(1));
+        assert_eq!(edit_costs.node_substitution_cost(&(4, 1), &(3, 1)), Some(1));
+        assert_eq!(edit_costs.min_node_substitution_cost(), 1);
+    }
+
+    #[test]
+    fn test_type_change_cost() {
+        // An activity (type 0) and an object (type 1) with the same label
+        let activity = make_graph(&[(1, 0), (2, 0)], &[(0, 1, 0)]);
+        let object = make_graph(&[(1, 0), (2, 1)], &[(0, 1, 0)]);
+        let mut edit_costs = GEDEditCosts::default();
+        assert_eq!(fast_ged(&activity, &object, &edit_costs), 1);
+
+        edit_costs.type_change = TypeChangeCost::Cost(5);
+        assert_eq!(edit_costs.node_substitution_cost(&(2, 0), &(2, 1)), Some(5));
+        // Deleting and inserting the vertex with its edge is cheaper
+        assert_eq!(fast_ged(&activity, &object, &edit_costs), 4);
+
+        edit_costs.type_change = TypeChangeCost::Forbidden;
+        assert_eq!(edit_costs.node_substitution_cost(&(2, 0), &(2, 1)), None);
+        assert_eq!(edit_costs.node_substitution_cost(&(2, 0), &(3, 0)), Some(1));
+        assert_eq!(fast_ged(&activity, &object, &edit_costs), 4);
+        assert_eq!(exact_ged(&activity, &object, &edit_costs, None), Some(4));
+    }
+
+    #[test]
+    fn test_label_similarity_substitution_cost() {
+        let mut label_similarity = LabelSimilarity::default();
+        label_similarity.insert(3, 4, 0.8);
+        label_similarity.insert(3, 5, 0.5);
+        let edit_costs = GEDEditCosts {
+            node_sub: 10,
+            vertex_type_sub: HashMap::from([(2, 4)]),
+            label_pair_sub: HashMap::from([((3, 6), 7)]),
+            label_similarity: Some(Arc::new(label_similarity)),
+            ..Default::default()
+        };
+        assert_eq!(edit_costs.node_substitution_cost(&(3, 1), &(4, 1)), Some(2));
+        assert_eq!(edit_costs.node_substitution_cost(&(5, 1), &(3, 1)), Some(5));
+        assert_eq!(edit_costs.node_substitution_cost(&(3, 2), &(5, 2)), Some(2));
+        assert_eq!(edit_costs.node_substitution_cost(&(3, 1), &(6, 1)), Some(7));
+        assert_eq!(
+            edit_costs.node_substitution_cost(&(4, 1), &(5, 1)),
+            Some(10)
+        );
+        // 4 * (1 - 0.8), rounded
+        assert_eq!(edit_costs.min_node_substitution_cost(), 1);
+
+        let g1 = make_graph(&[(3, 1), (7, 1)], &[(0, 1, 0)]);
+        let g2 = make_graph(&[(4, 1), (7, 1)], &[(0, 1, 0)]);
+        assert_eq!(fast_ged(&g1, &g2, &edit_costs), 2);
+        assert_eq!(exact_ged(&g1, &g2, &edit_costs, None), Some(2));
+    }
+
+    #[test]
+    fn test_read_substitution_costs() {
+        let path = std::env::temp_dir().join("cpd_test_substitution_costs.txt");
+        fs::write(&path, "# activities\nt 1 10\n\nl 3 4 1\n").unwrap();
+        let mut edit_costs = GEDEditCosts::default();
+        edit_costs.read_substitution_costs(&path).unwrap();
+        assert_eq!(edit_costs.vertex_type_sub, HashMap::from([(1, 10)]));
+        assert_eq!(edit_costs.label_pair_sub, HashMap::from([((3, 4), 1)]));
+
+        fs::write(&path, "t 1\n").unwrap();
+        assert!(edit_costs.read_substitution_costs(&path).is_err());
+        fs::write(&path, "x 1 2\n").unwrap();
+        assert!(edit_costs.read_substitution_costs(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_fast_ged_edge_deletion_and_insertion_costs() {
+        let g1 = make_graph(&[(1, 2), (2, 3)], &[(0, 1, 5)]);
+        let g2 = make_graph(&[(1, 2), (2, 3)], &[]);
+        let edit_costs = GEDEditCosts {
+            edge_del: 5,
+            edge_ins: 2,
+            ..Default::default()
+        };
+        assert_eq!(fast_ged(&g1, &g2, &edit_costs), 5);
+        assert_eq!(fast_ged(&g2, &g1, &edit_costs), 2);
+    }
+
+    #[test]
+    fn test_fast_ged_incoming_edges() {
+        // Same outgoing edges, but only g1 has an incoming edge at node 1
+        let g1 = make_graph(&[(1, 2), (2, 3), (3, 4)], &[(0, 1, 1), (2, 1, 1)]);
+        let g2 = make_graph(&[(1, 2), (2, 3), (3, 4)], &[(0, 1, 1)]);
+        assert_eq!(fast_ged(&g1, &g2, &GEDEditCosts::default()), 1);
+    }
+
+    #[test]
+    fn test_fast_ged_deleted_node_edges() {
+        // Deleting node 2 also deletes its two edges
+        let g1 = make_graph(
+            &[(1, 2), (2, 3), (3, 4)],
+            &[(0, 1, 1), (2, 0, 1), (1, 2, 1)],
+        );
+        let g2 = make_graph(&[(1, 2), (2, 3)], &[(0, 1, 1)]);
+        assert_eq!(fast_ged(&g1, &g2, &GEDEditCosts::default()), 3);
+        assert_eq!(fast_ged(&g2, &g1, &GEDEditCosts::default()), 3);
+    }
+
+    #[test]
+    fn test_fast_ged_uses_node_assignment_for_edges() {
+        // Edges are compared along the node assignment, not by their position
+        let g1 = make_graph(&[(1, 2), (2, 2), (3, 2)], &[(0, 1, 1), (0, 2, 2)]);
+        let g2 = make_graph(&[(1, 2), (2, 2), (3, 2)], &[(0, 2, 2), (0, 1, 1)]);
+        assert_eq!(fast_ged(&g1, &g2, &GEDEditCosts::default()), 0);
+        let g3 = make_graph(&[(1, 2), (2, 2), (3, 2)], &[(0, 1, 2), (0, 2, 1)]);
+        assert_eq!(fast_ged(&g1, &g3, &GEDEditCosts::default()), 2);
+    }
+}
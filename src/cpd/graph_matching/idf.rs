@@ -0,0 +1,182 @@
+use crate::{
+    cpd::candidate_matching::CandidateClasses,
+    data::graph::{EdgeVectorKey, Graph, VertexVectorKey},
+};
+use std::{collections::HashMap, hash::Hash, sync::OnceLock};
+
+/// Set of graphs the document frequencies of the IDF weights are counted over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdfSource {
+    /// All input graphs
+    Graphs,
+    /// All candidates of all input graphs
+    Candidates,
+}
+
+/// Inverse document frequency (IDF) weights of the vertex and edge vector keys.
+///
+/// The weight of a key is `ln((1 + N) / (1 + df)) + 1`, where `N` is the number of graphs of the
+/// `source` and `df` the number of those graphs that contain the key. Labels that appear in nearly
+/// every graph get a weight close to 1, rare labels a larger one. Keys that do not appear at all
+/// get the largest weight.
+///
+/// The weights are computed once by `AlgoGraphMatching::prepare`; before, all weights are 1.
+#[derive(Debug)]
+pub struct IdfWeights {
+    pub source: IdfSource,
+    weights: OnceLock<(IdfTable<VertexVectorKey>, IdfTable<EdgeVectorKey>)>,
+}
+
+#[derive(Debug)]
+struct IdfTable<T> {
+    weights: HashMap<T, f64>,
+    unknown_weight: f64,
+}
+
+impl<T: Eq + Hash + Copy> IdfTable<T> {
+    /// Table of the (Vector, Number of graphs with this vector) pairs
+    fn new<'a, I>(vectors: I) -> Self
+    where
+        I: Iterator<Item = (&'a HashMap<T, usize>, usize)>,
+        T: 'a,
+    {
+        let mut number_of_graphs = 0;
+        let mut document_frequencies: HashMap<T, usize> = HashMap::new();
+        for (vector, count) in vectors {
+            number_of_graphs += count;
+            for key in vector.keys() {
+                *document_frequencies.entry(*key).or_insert(0) += count;
+            }
+        }
+        let idf = |df: usize| ((1 + number_of_graphs) as f64 / (1 + df) as f64).ln() + 1.0;
+        IdfTable {
+            weights: document_frequencies
+                .into_iter()
+                .map(|(key, df)| (key, idf(df)))
+                .collect(),
+            unknown_weight: idf(0),
+        }
+    }
+
+    fn weight(&self, key: &T) -> f64 {
+        *self.weights.get(key).unwrap_or(&self.unknown_weight)
+    }
+}
+
+impl IdfWeights {
+    pub fn new(source: IdfSource) -> Self {
+        IdfWeights {
+            source,
+            weights: OnceLock::new(),
+        }
+    }
+
+    /// Counts the document frequencies over the graphs or candidates, depending on the `source`.
+    /// Only the first call has an effect. The candidates of an exact match class have the same
+    /// vectors, so the representative of every class counts as often as the class has candidates.
+    pub fn prepare(&self, graphs: &[Graph], candidates: &CandidateClasses) {
+        self.weights.get_or_init(|| {
+            let source_graphs: Vec<(&Graph, usize)> = match self.source {
+                IdfSource::Graphs => graphs.iter().map(|graph| (graph, 1)).collect(),
+                IdfSource::Candidates => (0..candidates.number_of_classes())
+                    .map(|class| {
+                        (
+                            &candidates.representative(class).graph,
+                            candidates.size(class),
+                        )
+                    })
+                    .collect(),
+            };
+            let vertex_vectors: Vec<_> = source_graphs
+                .iter()
+                .map(|(graph, count)| (graph.get_vertex_vector(), *count))
+                .collect();
+            let edge_vectors: Vec<_> = source_graphs
+                .iter()
+                .map(|(graph, count)| (graph.get_edge_vector(), *count))
+                .collect();
+            (
+                IdfTable::new(
+                    vertex_vectors
+                        .iter()
+                        .map(|(vector, count)| (vector.as_ref(), *count)),
+                ),
+                IdfTable::new(
+                    edge_vectors
+                        .iter()
+                        .map(|(vector, count)| (vector.as_ref(), *count)),
+                ),
+            )
+        });
+    }
+
+    /// Weight of a vertex vector key; 1 if the weights are not prepared
+    pub fn vertex_weight(&self, key: &VertexVectorKey) -> f64 {
+        self.weights
+            .get()
+            .map_or(1.0, |(vertex_weights, _)| vertex_weights.weight(key))
+    }
+
+    /// Weight of an edge vector key; 1 if the weights are not prepared
+    pub fn edge_weight(&self, key: &EdgeVectorKey) -> f64 {
+        self.weights
+            .get()
+            .map_or(1.0, |(_, edge_weights)| edge_weights.weight(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpd::{candidate_generation::Candidate, graph_matching::AlgoGraphMatching};
+
+    #[test]
+    fn test_idf_weights() {
+        // Label 1 ("System") is in every graph, label 2 only in the first one
+        let mut graphs = Vec::new();
+        for id in 0..3 {
+            let mut graph = Graph::new(id);
+            graph.create_vertex_with_data(1, 0);
+            graph.create_vertex_with_data(id + 2, 0);
+            graph.vertices.get_mut(0).unwrap().push(1, 0);
+            graphs.push(graph);
+        }
+        let idf = IdfWeights::new(IdfSource::Graphs);
+        assert_eq!(idf.vertex_weight(&(2, 0)), 1.0);
+
+        idf.prepare(&graphs, &CandidateClasses::default());
+        assert!((idf.vertex_weight(&(1, 0)) - 1.0).abs() < 1e-8);
+        assert!((idf.vertex_weight(&(2, 0)) - (2.0f64.ln() + 1.0)).abs() < 1e-8);
+        assert!((idf.vertex_weight(&(9, 0)) - (4.0f64.ln() + 1.0)).abs() < 1e-8);
+        assert!((idf.edge_weight(&(1, 0, 0, 2, 0)) - (2.0f64.ln() + 1.0)).abs() < 1e-8);
+
+        // Only the first preparation counts
+        idf.prepare(&graphs[..1], &CandidateClasses::default());
+        assert!((idf.vertex_weight(&(2, 0)) - (2.0f64.ln() + 1.0)).abs() < 1e-8);
+
+        // The second candidate is an exact match of the first one
+        let mut candidates = CandidateClasses::new(false);
+        candidates.extend(
+            [&graphs[0], &graphs[0], &graphs[1]]
+                .iter()
+                .map(|graph| {
+                    (
+                        0,
+                        Candidate {
+                            n_activity: 1,
+                            id_parent: 0,
+                            id_group: None,
+                            graph: (*graph).clone(),
+                        },
+                    )
+                })
+                .collect(),
+            &AlgoGraphMatching::VF2IsomorphismTest,
+        );
+        assert_eq!(candidates.number_of_classes(), 2);
+        let idf = IdfWeights::new(IdfSource::Candidates);
+        idf.prepare(&[], &candidates);
+        assert!((idf.vertex_weight(&(1, 0)) - 1.0).abs() < 1e-8);
+        assert!((idf.vertex_weight(&(2, 0)) - (4.0f64 / 3.0).ln() - 1.0).abs() < 1e-8);
+    }
+}
@@ -0,0 +1,119 @@
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+/// Similarities between vertex labels, e.g. between near-synonymous activity labels such as
+/// "Approve Invoice" and "Approve Invoice (manual)".
+///
+/// Equal labels have a similarity of 1.0, pairs without an entry a similarity of 0.0. The
+/// similarities are symmetric.
+#[derive(Debug, Default, Clone)]
+pub struct LabelSimilarity {
+    similarities: HashMap<(usize, usize), f64>,
+}
+
+#[derive(Debug)]
+pub struct LabelSimilarityParseError {
+    message: String,
+}
+
+impl fmt::Display for LabelSimilarityParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl LabelSimilarity {
+    /// Sets the similarity of two labels, clamped to 0.0 - 1.0
+    pub fn insert(&mut self, label: usize, other_label: usize, similarity: f64) {
+        let key = (label.min(other_label), label.max(other_label));
+        self.similarities.insert(key, similarity.clamp(0.0, 1.0));
+    }
+
+    /// Similarity of two labels between 0.0 and 1.0
+    pub fn similarity(&self, label: usize, other_label: usize) -> f64 {
+        if label == other_label {
+            return 1.0;
+        }
+        let key = (label.min(other_label), label.max(other_label));
+        *self.similarities.get(&key).unwrap_or(&0.0)
+    }
+
+    /// Highest similarity of two different labels
+    pub fn max_similarity(&self) -> f64 {
+        self.similarities
+            .values()
+            .fold(0.0, |max, sim| sim.max(max))
+    }
+
+    /// Reads label similarities from a plain text file:
+    ///
+    /// - s-line: similarity of two vertex labels (symmetric)
+    ///     - Format `s l1 l2 sim`: vertex labels `l1` and `l2`, similarity `sim` (0.0 - 1.0)
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn from_file<P>(path: P) -> Result<LabelSimilarity, LabelSimilarityParseError>
+    where
+        P: AsRef<Path>,
+    {
+        let content = fs::read_to_string(path).map_err(|_| LabelSimilarityParseError {
+            message: "Error reading file".to_string(),
+        })?;
+        let mut label_similarity = LabelSimilarity::default();
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let data: Vec<&str> = line.split_whitespace().collect();
+            let error = |message: &str| LabelSimilarityParseError {
+                message: format!("Line {}, {}", line_number + 1, message),
+            };
+            match data.as_slice() {
+                ["s", label, other_label, similarity] => {
+                    let label = label.parse().map_err(|_| error("invalid label"))?;
+                    let other_label = other_label.parse().map_err(|_| error("invalid label"))?;
+                    let similarity: f64 = similarity
+                        .parse()
+                        .map_err(|_| error("invalid similarity"))?;
+                    if !(0.0..=1.0).contains(&similarity) {
+                        return Err(error("similarity should be between 0.0 and 1.0"));
+                    }
+                    label_similarity.insert(label, other_label, similarity);
+                }
+                ["s", ..] => return Err(error("wrong number of values for 's'")),
+                [line_type, ..] => {
+                    return Err(error(&format!("unknown line type '{line_type}'")));
+                }
+                [] => {}
+            }
+        }
+        Ok(label_similarity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_similarity_from_file() {
+        let path = std::env::temp_dir().join("cpd_test_label_similarity.txt");
+        fs::write(
+            &path,
+            "# Approve Invoice (manual)\ns 3 4 0.8\n\ns 5 3 0.25\n",
+        )
+        .unwrap();
+        let label_similarity = LabelSimilarity::from_file(&path).unwrap();
+        assert_eq!(label_similarity.similarity(3, 4), 0.8);
+        assert_eq!(label_similarity.similarity(4, 3), 0.8);
+        assert_eq!(label_similarity.similarity(3, 5), 0.25);
+        assert_eq!(label_similarity.similarity(4, 5), 0.0);
+        assert_eq!(label_similarity.similarity(7, 7), 1.0);
+        assert_eq!(label_similarity.max_similarity(), 0.8);
+
+        for content in ["s 3 4\n", "s 3 4 1.5\n", "x 3 4 0.5\n", "s 3 a 0.5\n"] {
+            fs::write(&path, content).unwrap();
+            assert!(LabelSimilarity::from_file(&path).is_err());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
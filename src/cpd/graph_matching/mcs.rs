@@ -0,0 +1,233 @@
+use crate::data::graph::{Graph, GraphInvariants};
+use petgraph::visit::EdgeRef;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// (Vertex label, Vertex type) and sorted edge labels per ordered vertex pair of a graph
+struct LabeledAdjacency {
+    labels: Vec<(usize, usize)>,
+    edges: Vec<Vec<Vec<usize>>>,
+}
+
+impl LabeledAdjacency {
+    fn new(graph: &Graph) -> Self {
+        let digraph = graph.get_digraph();
+        let n = digraph.node_count();
+        let mut edges = vec![vec![Vec::new(); n]; n];
+        for edge in digraph.edge_references() {
+            edges[edge.source().index()][edge.target().index()].push(*edge.weight());
+        }
+        for labels in edges.iter_mut().flatten() {
+            labels.sort_unstable();
+        }
+        LabeledAdjacency {
+            labels: digraph.node_weights().copied().collect(),
+            edges,
+        }
+    }
+
+    /// Edge labels from `u` to `v` and from `v` to `u`
+    fn relation(&self, u: usize, v: usize) -> (&[usize], &[usize]) {
+        (&self.edges[u][v], &self.edges[v][u])
+    }
+}
+
+/// Vertices of both graphs that can still be mapped onto each other: same label and type, and
+/// the same edges to all mapped vertices
+#[derive(Clone)]
+struct Bidomain {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    /// Whether the vertices are adjacent to a mapped vertex
+    is_adjacent: bool,
+}
+
+struct McSplit<'a> {
+    one: &'a LabeledAdjacency,
+    other: &'a LabeledAdjacency,
+    deadline: Instant,
+    timed_out: bool,
+    best: usize,
+}
+
+impl McSplit<'_> {
+    fn search(&mut self, mut domains: Vec<Bidomain>, size: usize) {
+        if self.timed_out || Instant::now() >= self.deadline {
+            self.timed_out = true;
+            return;
+        }
+        self.best = self.best.max(size);
+        let bound: usize = domains
+            .iter()
+            .map(|domain| domain.left.len().min(domain.right.len()))
+            .sum();
+        if size + bound <= self.best {
+            return;
+        }
+        // Only vertices adjacent to the mapped ones keep the common subgraph connected
+        let Some(i_domain) = domains
+            .iter()
+            .enumerate()
+            .filter(|(_, domain)| size == 0 || domain.is_adjacent)
+            .min_by_key(|(_, domain)| domain.left.len().max(domain.right.len()))
+            .map(|(i_domain, _)| i_domain)
+        else {
+            return;
+        };
+        let v = domains[i_domain].left[0];
+        for w in domains[i_domain].right.clone() {
+            if self.one.edges[v][v] != self.other.edges[w][w] {
+                continue;
+            }
+            let refined = self.refine(&domains, v, w);
+            self.search(refined, size + 1);
+        }
+        // Leave `v` unmapped
+        domains[i_domain].left.swap_remove(0);
+        if domains[i_domain].left.is_empty() {
+            domains.swap_remove(i_domain);
+        }
+        self.search(domains, size);
+    }
+
+    /// Splits every domain by the edges of its vertices to `v` and `w`, respectively
+    fn refine(&self, domains: &[Bidomain], v: usize, w: usize) -> Vec<Bidomain> {
+        let mut refined = Vec::with_capacity(domains.len());
+        for domain in domains.iter() {
+            let mut splits: HashMap<(&[usize], &[usize]), Bidomain> = HashMap::new();
+            for u in domain.left.iter().filter(|u| **u != v) {
+                splits
+                    .entry(self.one.relation(v, *u))
+                    .or_insert_with(|| Bidomain {
+                        left: Vec::new(),
+                        right: Vec::new(),
+                        is_adjacent: domain.is_adjacent,
+                    })
+                    .left
+                    .push(*u);
+            }
+            for u in domain.right.iter().filter(|u| **u != w) {
+                if let Some(split) = splits.get_mut(&self.other.relation(w, *u)) {
+                    split.right.push(*u);
+                }
+            }
+            for (relation, mut split) in splits {
+                if split.right.is_empty() {
+                    continue;
+                }
+                split.is_adjacent |= !relation.0.is_empty() || !relation.1.is_empty();
+                refined.push(split);
+            }
+        }
+        refined
+    }
+}
+
+/// Number of vertices of the maximum common connected induced subgraph of two graphs that
+/// respects the vertex labels and types, the edge labels, and the edge directions.
+///
+/// McSplit branch and bound (McCreesh et al., 2017): the unmapped vertices are partitioned into
+/// domains of vertices that can be mapped onto each other, and the sum of the smaller sides of
+/// all domains bounds the number of vertices that can still be mapped. If the search takes
+/// longer than the `time_limit`, the largest common subgraph found so far is returned.
+pub(super) fn mcs_size(one_graph: &Graph, other_graph: &Graph, time_limit: Duration) -> usize {
+    let one = LabeledAdjacency::new(one_graph);
+    let other = LabeledAdjacency::new(other_graph);
+    let mut domains_by_label: HashMap<(usize, usize), Bidomain> = HashMap::new();
+    for (v, label) in one.labels.iter().enumerate() {
+        domains_by_label
+            .entry(*label)
+            .or_insert_with(|| Bidomain {
+                left: Vec::new(),
+                right: Vec::new(),
+                is_adjacent: false,
+            })
+            .left
+            .push(v);
+    }
+    for (w, label) in other.labels.iter().enumerate() {
+        if let Some(domain) = domains_by_label.get_mut(label) {
+            domain.right.push(w);
+        }
+    }
+    let domains: Vec<Bidomain> = domains_by_label
+        .into_values()
+        .filter(|domain| !domain.right.is_empty())
+        .collect();
+    let mut mcsplit = McSplit {
+        one: &one,
+        other: &other,
+        deadline: Instant::now() + time_limit,
+        timed_out: false,
+        best: 0,
+    };
+    mcsplit.search(domains, 0);
+    mcsplit.best
+}
+
+/// Upper bound of the maximum common subgraph similarity `|MCS| / max(|G1|, |G2|)` of two graphs
+/// with the invariants `one` and `other`: only vertices of the same type can be mapped.
+pub(super) fn mcs_similarity_bound(one: &GraphInvariants, other: &GraphInvariants) -> f64 {
+    let max_size = one.number_of_vertices.max(other.number_of_vertices);
+    if max_size == 0 {
+        return 1.0;
+    }
+    let other_type_counts: HashMap<usize, usize> = other.type_counts.iter().copied().collect();
+    let common: usize = one
+        .type_counts
+        .iter()
+        .map(|(vertex_type, count)| (*count).min(*other_type_counts.get(vertex_type).unwrap_or(&0)))
+        .sum();
+    common as f64 / max_size as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpd::test_utils::make_graph;
+
+    #[test]
+    fn test_mcs_size() {
+        let time_limit = Duration::from_secs(10);
+        // Path 1 -> 2 -> 3 -> 4
+        let path = make_graph(
+            &[(1, 0), (2, 0), (3, 0), (4, 0)],
+            &[(0, 1, 0), (1, 2, 0), (2, 3, 0)],
+        );
+        assert_eq!(mcs_size(&path, &path, time_limit), 4);
+        // Same path in another vertex order
+        let other_path = make_graph(
+            &[(4, 0), (3, 0), (2, 0), (1, 0)],
+            &[(3, 2, 0), (2, 1, 0), (1, 0, 0)],
+        );
+        assert_eq!(mcs_size(&path, &other_path, time_limit), 4);
+        // 1 -> 2 -> 3 and 5 -> 4: the common subgraph must be connected
+        let split_path = make_graph(
+            &[(1, 0), (2, 0), (3, 0), (4, 0), (5, 0)],
+            &[(0, 1, 0), (1, 2, 0), (4, 3, 0)],
+        );
+        assert_eq!(mcs_size(&path, &split_path, time_limit), 3);
+        // Reversed edge 3 <- 4
+        let reversed = make_graph(
+            &[(1, 0), (2, 0), (3, 0), (4, 0)],
+            &[(0, 1, 0), (1, 2, 0), (3, 2, 0)],
+        );
+        assert_eq!(mcs_size(&path, &reversed, time_limit), 3);
+        // Other edge label 2 -> 3 and other vertex type of 4
+        let relabeled = make_graph(
+            &[(1, 0), (2, 0), (3, 0), (4, 1)],
+            &[(0, 1, 0), (1, 2, 7), (2, 3, 0)],
+        );
+        assert_eq!(mcs_size(&path, &relabeled, time_limit), 2);
+        // Repeated labels: cycle of four equal vertices vs path of three
+        let cycle = make_graph(
+            &[(1, 0), (1, 0), (1, 0), (1, 0)],
+            &[(0, 1, 0), (1, 2, 0), (2, 3, 0), (3, 0, 0)],
+        );
+        let short_path = make_graph(&[(1, 0), (1, 0), (1, 0)], &[(0, 1, 0), (1, 2, 0)]);
+        assert_eq!(mcs_size(&cycle, &short_path, time_limit), 3);
+        assert_eq!(mcs_size(&path, &cycle, time_limit), 1);
+    }
+}
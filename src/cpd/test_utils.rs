@@ -0,0 +1,36 @@
+use crate::{cpd::candidate_generation::Candidate, data::graph::Graph};
+
+/// Graph with the id 1 from the (label, vertex type) of every vertex and the (from, to, label) of
+/// every edge
+pub fn make_graph(vertices: &[(usize, usize)], edges: &[(usize, usize, usize)]) -> Graph {
+    let mut g = Graph::new(1);
+    for (label, vertex_type) in vertices {
+        g.create_vertex_with_data(*label, *vertex_type);
+    }
+    for (from, to, label) in edges {
+        g.vertices.get_mut(*from).unwrap().push(*to, *label);
+    }
+    g
+}
+
+/// Path of vertices of type 1 with the labels, connected by edges with the label 0
+pub fn make_path_graph(id: usize, labels: &[usize]) -> Graph {
+    let mut g = Graph::new(id);
+    for label in labels {
+        g.create_vertex_with_data(*label, 1);
+    }
+    for i_vertex in 1..labels.len() {
+        g.vertices.get_mut(i_vertex - 1).unwrap().push(i_vertex, 0);
+    }
+    g
+}
+
+/// Candidate of a path graph with the id 0 (`make_path_graph`), one activity vertex per label
+pub fn make_candidate(labels: &[usize]) -> Candidate {
+    Candidate {
+        n_activity: labels.len(),
+        id_parent: 0,
+        id_group: None,
+        graph: make_path_graph(0, labels),
+    }
+}
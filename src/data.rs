@@ -1,4 +1,5 @@
 pub mod edge;
 pub mod graph;
+pub mod taxonomy;
 pub mod utils;
 pub mod vertex;
@@ -2,14 +2,19 @@ use petgraph::graph::DiGraph;
 
 use crate::data::edge::Edge;
 use crate::data::vertex::Vertex;
+use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::fs::File;
+use std::hash::Hash;
 use std::io::BufRead;
 use std::path::Path;
-use std::sync::{Arc, OnceLock};
+use std::sync::{Arc, OnceLock, RwLock};
 use std::{fmt, io};
 
-use super::utils::{build_edge_vector, build_vertex_vector};
+use super::utils::{
+    build_activity_core, build_edge_vector, build_invariants, build_object_context, build_spectrum,
+    build_vertex_vector, build_wl_features,
+};
 
 /// (Vetex Label, Vertex Type)
 pub type VertexVectorKey = (usize, usize);
@@ -17,6 +22,67 @@ pub type VertexVectorKey = (usize, usize);
 /// (From Vertex Label, From Vertex Type, Edge Label, To Vertex Label, To Vertex Type)
 pub type EdgeVectorKey = (usize, usize, usize, usize, usize);
 
+/// Matrix of a graph whose eigenvalues form its spectrum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpectralMatrix {
+    /// Label-weighted adjacency matrix `A`
+    Adjacency,
+    /// Normalized Laplacian `I - D^(-1/2) A D^(-1/2)` of the label-weighted adjacency matrix
+    NormalizedLaplacian,
+}
+
+/// Cheap isomorphism invariants of a graph: isomorphic graphs have equal invariants, so graphs
+/// with different invariants can never match exactly.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GraphInvariants {
+    pub number_of_vertices: usize,
+    pub number_of_edges: usize,
+    /// (Vertex type, Number of vertices) in ascending order
+    pub type_counts: Vec<(usize, usize)>,
+    /// Hash of the multiset of the vertex vector keys (label, type) and the multiset of the edge
+    /// vector keys (labels and types of both ends and the edge label); both are sorted before
+    /// hashing, so they do not depend on the vertex order
+    pub label_hash: u64,
+    /// (Number of successors, Number of predecessors) of every vertex in ascending order
+    pub degree_sequence: Vec<(usize, usize)>,
+}
+
+/// Values built on first use per key, e.g. per parameter set of a graph feature
+#[derive(Debug)]
+struct KeyedCache<K, V>(RwLock<HashMap<K, Arc<V>>>);
+
+impl<K, V> Default for KeyedCache<K, V> {
+    fn default() -> Self {
+        KeyedCache(RwLock::new(HashMap::new()))
+    }
+}
+
+impl<K: Clone, V> Clone for KeyedCache<K, V> {
+    fn clone(&self) -> Self {
+        KeyedCache(RwLock::new(self.0.read().unwrap().clone()))
+    }
+}
+
+impl<K: Eq + Hash, V> KeyedCache<K, V> {
+    fn get_or_init<Q, F>(&self, key: &Q, init: F) -> Arc<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ToOwned<Owned = K> + ?Sized,
+        F: FnOnce() -> V,
+    {
+        if let Some(value) = self.0.read().unwrap().get(key) {
+            return value.clone();
+        }
+        let value = Arc::new(init());
+        self.0
+            .write()
+            .unwrap()
+            .entry(key.to_owned())
+            .or_insert(value)
+            .clone()
+    }
+}
+
 #[derive(Debug)]
 pub struct GraphSetParseError {
     message: String,
@@ -35,6 +101,15 @@ pub struct Graph {
     vertex_vector: OnceLock<Arc<HashMap<VertexVectorKey, usize>>>,
     edge_vector: OnceLock<Arc<HashMap<EdgeVectorKey, usize>>>,
     digraph: OnceLock<Arc<DiGraph<(usize, usize), usize>>>,
+    invariants: OnceLock<GraphInvariants>,
+    /// WL features per number of iterations
+    wl_features: KeyedCache<usize, HashMap<u64, usize>>,
+    /// Eigenvalues in descending order per matrix
+    spectrum: KeyedCache<SpectralMatrix, Vec<f64>>,
+    /// Activity core per activity vertex types
+    activity_cores: KeyedCache<Vec<usize>, Graph>,
+    /// Object context of the activity core per activity vertex types
+    object_contexts: KeyedCache<Vec<usize>, Graph>,
 }
 
 impl Graph {
@@ -45,6 +120,11 @@ impl Graph {
             vertex_vector: OnceLock::new(),
             edge_vector: OnceLock::new(),
             digraph: OnceLock::new(),
+            invariants: OnceLock::new(),
+            wl_features: KeyedCache::default(),
+            spectrum: KeyedCache::default(),
+            activity_cores: KeyedCache::default(),
+            object_contexts: KeyedCache::default(),
         }
     }
 
@@ -76,6 +156,13 @@ impl Graph {
             .collect()
     }
 
+    pub fn get_vertices_by_types(&self, vertex_types: &[usize]) -> Vec<&Vertex> {
+        self.vertices
+            .iter()
+            .filter(|vertex| vertex_types.contains(&vertex.vertex_type))
+            .collect()
+    }
+
     pub fn get_vertex_vector(&self) -> Arc<HashMap<(usize, usize), usize>> {
         self.vertex_vector
             .get_or_init(|| {
@@ -94,6 +181,41 @@ impl Graph {
             .clone()
     }
 
+    /// Returns the Weisfeiler-Lehman subtree features, building them on first use of the
+    /// number of iterations.
+    pub fn get_wl_features(&self, iterations: usize) -> Arc<HashMap<u64, usize>> {
+        self.wl_features
+            .get_or_init(&iterations, || build_wl_features(self, iterations))
+    }
+
+    /// Returns the eigenvalues of the `matrix` in descending order, building them on first use
+    /// of the matrix.
+    pub fn get_spectrum(&self, matrix: SpectralMatrix) -> Arc<Vec<f64>> {
+        self.spectrum
+            .get_or_init(&matrix, || build_spectrum(self, matrix))
+    }
+
+    /// Returns the vertices of the activity vertex types and the edges between them, building
+    /// them on first use of the activity vertex types.
+    pub fn get_activity_core(&self, activity_vertex_types: &[usize]) -> Arc<Graph> {
+        self.activity_cores.get_or_init(activity_vertex_types, || {
+            build_activity_core(self, activity_vertex_types)
+        })
+    }
+
+    /// Returns the object vertices, the edges incident to them and the activity vertices they are
+    /// attached to, building them on first use of the activity vertex types.
+    pub fn get_object_context(&self, activity_vertex_types: &[usize]) -> Arc<Graph> {
+        self.object_contexts.get_or_init(activity_vertex_types, || {
+            build_object_context(self, activity_vertex_types)
+        })
+    }
+
+    /// Returns the isomorphism invariants, building them on first use.
+    pub fn get_invariants(&self) -> &GraphInvariants {
+        self.invariants.get_or_init(|| build_invariants(self))
+    }
+
     /// Returns an Arc-wrapped digraph, building it on first use.
     pub fn get_digraph(&self) -> Arc<DiGraph<(usize, usize), usize>> {
         self.digraph
@@ -448,4 +570,38 @@ mod tests {
         let di_graph2 = graph.get_digraph();
         assert!(Arc::ptr_eq(&di_graph, &di_graph2));
     }
+
+    #[test]
+    fn test_wl_features_cached_per_iterations() {
+        let mut graph = Graph::new(1);
+        graph.create_vertex_with_data(1, 2);
+        graph.create_vertex_with_data(2, 2);
+        graph.vertices.get_mut(0).unwrap().push(1, 0);
+
+        let one_iteration = graph.get_wl_features(1);
+        let two_iterations = graph.get_wl_features(2);
+        assert_ne!(one_iteration, two_iterations);
+        assert!(Arc::ptr_eq(&one_iteration, &graph.get_wl_features(1)));
+        assert!(Arc::ptr_eq(&two_iterations, &graph.get_wl_features(2)));
+    }
+
+    #[test]
+    fn test_spectrum_cached_per_matrix() {
+        let mut graph = Graph::new(1);
+        graph.create_vertex_with_data(1, 2);
+        graph.create_vertex_with_data(2, 2);
+        graph.vertices.get_mut(0).unwrap().push(1, 0);
+
+        let adjacency = graph.get_spectrum(SpectralMatrix::Adjacency);
+        let laplacian = graph.get_spectrum(SpectralMatrix::NormalizedLaplacian);
+        assert_ne!(adjacency, laplacian);
+        assert!(Arc::ptr_eq(
+            &adjacency,
+            &graph.get_spectrum(SpectralMatrix::Adjacency)
+        ));
+        assert!(Arc::ptr_eq(
+            &laplacian,
+            &graph.get_spectrum(SpectralMatrix::NormalizedLaplacian)
+        ));
+    }
 }
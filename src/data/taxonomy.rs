@@ -0,0 +1,217 @@
+use crate::data::graph::Graph;
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+/// Label hierarchy per vertex type, e.g. specific activity labels that roll up to activity
+/// categories, and object labels that roll up to object classes.
+///
+/// A label without a parent is a root. The levels count down from the deepest labels of a vertex
+/// type: generalizing a label to `level` replaces it by its ancestor at the depth
+/// `max_depth - level` below its root, or keeps it if it is not that deep. Two labels therefore
+/// become equal exactly at the level of their lowest common ancestor, even if they lie at
+/// different depths.
+#[derive(Debug, Default, Clone)]
+pub struct Taxonomy {
+    /// (Vertex type, Label) -> Parent label
+    parents: HashMap<(usize, usize), usize>,
+    /// Vertex type -> Largest number of ancestors of its labels
+    max_depths: HashMap<usize, usize>,
+}
+
+#[derive(Debug)]
+pub struct TaxonomyParseError {
+    message: String,
+}
+
+impl fmt::Display for TaxonomyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Taxonomy {
+    /// Reads a taxonomy from a plain text file:
+    ///
+    /// - p-line: parent of a vertex label
+    ///     - Format `p TYPE CHILD PARENT`: the label `CHILD` of vertices of type `TYPE` rolls up
+    ///       to the label `PARENT`
+    ///
+    /// Every label has at most one parent, and the hierarchy must not contain cycles. Empty lines
+    /// and lines starting with `#` are ignored.
+    pub fn from_file<P>(path: P) -> Result<Taxonomy, TaxonomyParseError>
+    where
+        P: AsRef<Path>,
+    {
+        let content = fs::read_to_string(path).map_err(|_| TaxonomyParseError {
+            message: "Error reading file".to_string(),
+        })?;
+        let mut taxonomy = Taxonomy::default();
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let data: Vec<&str> = line.split_whitespace().collect();
+            let error = |message: &str| TaxonomyParseError {
+                message: format!("Line {}, {}", line_number + 1, message),
+            };
+            match data.as_slice() {
+                ["p", vertex_type, child, parent] => {
+                    let vertex_type = vertex_type
+                        .parse()
+                        .map_err(|_| error("invalid vertex type"))?;
+                    let child = child.parse().map_err(|_| error("invalid label"))?;
+                    let parent = parent.parse().map_err(|_| error("invalid label"))?;
+                    if let Some(other_parent) =
+                        taxonomy.parents.insert((vertex_type, child), parent)
+                        && other_parent != parent
+                    {
+                        return Err(error(&format!(
+                            "label {child} has the parents {other_parent} and {parent}"
+                        )));
+                    }
+                }
+                ["p", ..] => return Err(error("wrong number of values for 'p'")),
+                [line_type, ..] => {
+                    return Err(error(&format!("unknown line type '{line_type}'")));
+                }
+                [] => {}
+            }
+        }
+        for (vertex_type, label) in taxonomy.parents.keys() {
+            let depth = taxonomy.depth(*vertex_type, *label);
+            if depth > taxonomy.parents.len() {
+                return Err(TaxonomyParseError {
+                    message: format!("Cycle in the ancestors of label {label}, type {vertex_type}"),
+                });
+            }
+            let max_depth = taxonomy.max_depths.entry(*vertex_type).or_insert(0);
+            *max_depth = (*max_depth).max(depth);
+        }
+        Ok(taxonomy)
+    }
+
+    /// Ancestor `level` steps above the label; the root if the hierarchy is not that deep
+    pub fn ancestor(&self, vertex_type: usize, label: usize, level: usize) -> usize {
+        let mut label = label;
+        for _ in 0..level {
+            match self.parents.get(&(vertex_type, label)) {
+                Some(parent) => label = *parent,
+                None => break,
+            }
+        }
+        label
+    }
+
+    /// Number of ancestors of the label; stops counting above the number of labels with a parent
+    fn depth(&self, vertex_type: usize, label: usize) -> usize {
+        let mut label = label;
+        let mut depth = 0;
+        while let Some(parent) = self.parents.get(&(vertex_type, label)) {
+            label = *parent;
+            depth += 1;
+            if depth > self.parents.len() {
+                break;
+            }
+        }
+        depth
+    }
+
+    /// Highest level at which a label is still generalized
+    pub fn max_level(&self) -> usize {
+        self.max_depths.values().copied().max().unwrap_or(0)
+    }
+
+    /// Ancestor of the label at the `level`, i.e. at the depth `max_depth - level` of its vertex
+    /// type; the label itself if it is not deeper. Generalizing to a higher level never undoes a
+    /// lower one: the label at level `l` generalized to level `k >= l` is the label at level `k`.
+    pub fn generalize_label(&self, vertex_type: usize, label: usize, level: usize) -> usize {
+        let max_depth = self.max_depths.get(&vertex_type).copied().unwrap_or(0);
+        let depth = self.depth(vertex_type, label);
+        self.ancestor(
+            vertex_type,
+            label,
+            depth.saturating_sub(max_depth.saturating_sub(level)),
+        )
+    }
+
+    /// Copy of the graph with every vertex label generalized to the `level`.
+    /// The id, the vertex types, and the edges stay the same.
+    pub fn generalize(&self, graph: &Graph, level: usize) -> Graph {
+        let mut generalized = Graph::new(graph.id);
+        for vertex in graph.vertices.iter() {
+            generalized.create_vertex_with_data(
+                self.generalize_label(vertex.vertex_type, vertex.label, level),
+                vertex.vertex_type,
+            );
+        }
+        for (vertex, generalized_vertex) in
+            graph.vertices.iter().zip(generalized.vertices.iter_mut())
+        {
+            generalized_vertex.edges = vertex.edges.clone();
+        }
+        generalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taxonomy() {
+        let path = std::env::temp_dir().join("cpd_test_taxonomy.txt");
+        // Activities (type 1): 3, 4 -> 10 (Approve) -> 20 (Finance); objects (type 2): 5 -> 11
+        fs::write(
+            &path,
+            "# Activities\np 1 3 10\np 1 4 10\np 1 10 20\n\np 2 5 11\n",
+        )
+        .unwrap();
+        let taxonomy = Taxonomy::from_file(&path).unwrap();
+        assert_eq!(taxonomy.ancestor(1, 3, 0), 3);
+        assert_eq!(taxonomy.ancestor(1, 3, 1), 10);
+        assert_eq!(taxonomy.ancestor(1, 4, 2), 20);
+        assert_eq!(taxonomy.ancestor(1, 4, 5), 20);
+        // The hierarchy is per vertex type
+        assert_eq!(taxonomy.ancestor(2, 3, 1), 3);
+        assert_eq!(taxonomy.max_level(), 2);
+
+        let mut graph = Graph::new(7);
+        graph.create_vertex_with_data(3, 1);
+        graph.create_vertex_with_data(5, 2);
+        graph.vertices.get_mut(0).unwrap().push(1, 4);
+        let generalized = taxonomy.generalize(&graph, 1);
+        assert_eq!(generalized.id, 7);
+        assert_eq!(generalized.vertices[0].label, 10);
+        assert_eq!(generalized.vertices[1].label, 11);
+        assert_eq!(generalized.vertices[1].vertex_type, 2);
+        assert_eq!(
+            generalized.get_edge_vector().get(&(10, 1, 4, 11, 2)),
+            Some(&1)
+        );
+
+        // Unbalanced hierarchy: 3 and 10 meet at their lowest common ancestor 10 at level 1,
+        // 3 and 4 only at 20 at level 2
+        fs::write(&path, "p 1 3 10\np 1 10 20\np 1 4 20\n").unwrap();
+        let taxonomy = Taxonomy::from_file(&path).unwrap();
+        assert_eq!(taxonomy.max_level(), 2);
+        assert_eq!(taxonomy.generalize_label(1, 3, 0), 3);
+        assert_eq!(taxonomy.generalize_label(1, 10, 0), 10);
+        assert_eq!(taxonomy.generalize_label(1, 3, 1), 10);
+        assert_eq!(taxonomy.generalize_label(1, 10, 1), 10);
+        assert_eq!(taxonomy.generalize_label(1, 4, 1), 4);
+        assert_eq!(taxonomy.generalize_label(1, 3, 2), 20);
+        assert_eq!(taxonomy.generalize_label(1, 4, 2), 20);
+        assert_eq!(taxonomy.generalize_label(1, 10, 5), 20);
+
+        for content in [
+            "p 1 3\n",
+            "p 1 3 10\np 1 3 11\n",
+            "p 1 3 4\np 1 4 3\n",
+            "x 1 2 3\n",
+        ] {
+            fs::write(&path, content).unwrap();
+            assert!(Taxonomy::from_file(&path).is_err());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
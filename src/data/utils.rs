@@ -1,8 +1,12 @@
 use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 use crate::data::edge::Edge;
 
-use super::{graph::EdgeVectorKey, graph::Graph, graph::VertexVectorKey, vertex::Vertex};
+use super::{
+    graph::EdgeVectorKey, graph::Graph, graph::GraphInvariants, graph::SpectralMatrix,
+    graph::VertexVectorKey, vertex::Vertex,
+};
 
 /// Check if the vertices of a graph are connected through edges
 pub fn vertices_are_connected(vertices: &Vec<&Vertex>) -> bool {
@@ -59,6 +63,247 @@ pub fn build_edge_vector(graph: &Graph) -> HashMap<EdgeVectorKey, usize> {
     result
 }
 
+/// Build the Weisfeiler-Lehman subtree features of a graph -> Number of vertices per WL label of
+/// the iterations 0 to `iterations`.
+///
+/// The WL label of a vertex is its (label, vertex type) in iteration 0. In each further iteration,
+/// the new WL label hashes the previous one with the sorted (edge label, WL label) pairs of the
+/// outgoing and of the incoming neighbours. The hashes do not depend on the graph, so equal WL
+/// labels of different graphs describe equal directed neighbourhoods.
+pub fn build_wl_features(graph: &Graph, iterations: usize) -> HashMap<u64, usize> {
+    let mut incoming: Vec<Vec<&Edge>> = vec![Vec::new(); graph.vertices.len()];
+    for edge in graph.vertices.iter().flat_map(|v| &v.edges) {
+        incoming[edge.to].push(edge);
+    }
+
+    let mut wl_labels: Vec<u64> = graph
+        .vertices
+        .iter()
+        .map(|v| hash_of(&(v.label, v.vertex_type)))
+        .collect();
+    let mut result = HashMap::new();
+    for iteration in 0..=iterations {
+        if iteration > 0 {
+            wl_labels = graph
+                .vertices
+                .iter()
+                .map(|v| {
+                    let mut outgoing: Vec<(usize, u64)> = v
+                        .edges
+                        .iter()
+                        .map(|e| (e.e_label, wl_labels[e.to]))
+                        .collect();
+                    let mut incoming: Vec<(usize, u64)> = incoming[v.id]
+                        .iter()
+                        .map(|e| (e.e_label, wl_labels[e.from]))
+                        .collect();
+                    outgoing.sort_unstable();
+                    incoming.sort_unstable();
+                    hash_of(&(wl_labels[v.id], outgoing, incoming))
+                })
+                .collect();
+        }
+        for wl_label in wl_labels.iter() {
+            *result.entry(*wl_label).or_insert(0) += 1;
+        }
+    }
+    result
+}
+
+/// Copies the vertices of the activity vertex types and the edges between them into a new graph
+pub fn build_activity_core(graph: &Graph, activity_vertex_types: &[usize]) -> Graph {
+    let mut core = Graph::new(graph.id);
+    let activity_vertices = graph.get_vertices_by_types(activity_vertex_types);
+    let mut vertex_id_mapping: HashMap<usize, usize> = HashMap::new();
+    for vertex in activity_vertices.iter() {
+        let core_vertex = core.create_vertex_with_data(vertex.label, vertex.vertex_type);
+        vertex_id_mapping.insert(vertex.id, core_vertex.id);
+    }
+    for vertex in activity_vertices.iter() {
+        let core_vertex_id = vertex_id_mapping[&vertex.id];
+        for edge in vertex.edges.iter() {
+            if let Some(core_to_id) = vertex_id_mapping.get(&edge.to) {
+                core.vertices[core_vertex_id].push(*core_to_id, edge.e_label);
+            }
+        }
+    }
+    core
+}
+
+/// Copies the object context of the activity core into a new graph: the vertices that are not of
+/// the activity vertex types, the edges with at least one of them as an end, and the activity
+/// vertices these edges attach the context to. The edges between activity vertices are left out.
+pub fn build_object_context(graph: &Graph, activity_vertex_types: &[usize]) -> Graph {
+    let mut context = Graph::new(graph.id);
+    let is_activity =
+        |vertex_id: usize| activity_vertex_types.contains(&graph.vertices[vertex_id].vertex_type);
+    let context_edges: Vec<&Edge> = graph
+        .vertices
+        .iter()
+        .flat_map(|vertex| vertex.edges.iter())
+        .filter(|edge| !is_activity(edge.from) || !is_activity(edge.to))
+        .collect();
+    let attached: HashSet<usize> = context_edges
+        .iter()
+        .flat_map(|edge| [edge.from, edge.to])
+        .collect();
+    let mut vertex_id_mapping: HashMap<usize, usize> = HashMap::new();
+    for vertex in graph.vertices.iter() {
+        if !is_activity(vertex.id) || attached.contains(&vertex.id) {
+            let context_vertex = context.create_vertex_with_data(vertex.label, vertex.vertex_type);
+            vertex_id_mapping.insert(vertex.id, context_vertex.id);
+        }
+    }
+    for edge in context_edges {
+        context.vertices[vertex_id_mapping[&edge.from]]
+            .push(vertex_id_mapping[&edge.to], edge.e_label);
+    }
+    context
+}
+
+/// Builds the isomorphism invariants of a graph. The degrees count distinct neighbours, so
+/// parallel edges with different labels only change the number of edges.
+pub fn build_invariants(graph: &Graph) -> GraphInvariants {
+    let mut type_counts: HashMap<usize, usize> = HashMap::new();
+    let mut successors: Vec<HashSet<usize>> = vec![HashSet::new(); graph.vertices.len()];
+    let mut predecessors: Vec<HashSet<usize>> = vec![HashSet::new(); graph.vertices.len()];
+    for vertex in graph.vertices.iter() {
+        *type_counts.entry(vertex.vertex_type).or_default() += 1;
+        for edge in vertex.edges.iter() {
+            successors[vertex.id].insert(edge.to);
+            predecessors[edge.to].insert(vertex.id);
+        }
+    }
+    let mut type_counts: Vec<(usize, usize)> = type_counts.into_iter().collect();
+    type_counts.sort_unstable();
+    let mut vertex_labels: Vec<(VertexVectorKey, usize)> = graph
+        .get_vertex_vector()
+        .iter()
+        .map(|(key, count)| (*key, *count))
+        .collect();
+    vertex_labels.sort_unstable();
+    let mut edge_labels: Vec<(EdgeVectorKey, usize)> = graph
+        .get_edge_vector()
+        .iter()
+        .map(|(key, count)| (*key, *count))
+        .collect();
+    edge_labels.sort_unstable();
+    let mut degree_sequence: Vec<(usize, usize)> = successors
+        .iter()
+        .zip(predecessors.iter())
+        .map(|(successors, predecessors)| (successors.len(), predecessors.len()))
+        .collect();
+    degree_sequence.sort_unstable();
+    GraphInvariants {
+        number_of_vertices: graph.vertices.len(),
+        number_of_edges: graph.vertices.iter().map(|vertex| vertex.edges.len()).sum(),
+        type_counts,
+        label_hash: hash_of(&(vertex_labels, edge_labels)),
+        degree_sequence,
+    }
+}
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Weight between 1.0 and 2.0 of a label; equal labels of different graphs get equal weights
+fn label_weight<T: Hash>(label: &T) -> f64 {
+    1.0 + (hash_of(label) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Build the eigenvalue spectrum of a graph -> Eigenvalues of the `matrix`, sorted in descending
+/// order.
+///
+/// Both matrices are built from the symmetric, label-weighted adjacency matrix `A`: the diagonal
+/// entry of a vertex is the weight of its (label, vertex type), the entry of two adjacent
+/// vertices the sum of the weights of the edge labels between them in both directions. The
+/// weights are between 1.0 and 2.0 and derived from the labels only, so equal labels of different
+/// graphs get equal weights.
+pub fn build_spectrum(graph: &Graph, matrix: SpectralMatrix) -> Vec<f64> {
+    let n = graph.vertices.len();
+    let mut adjacency = vec![vec![0.0; n]; n];
+    for vertex in graph.vertices.iter() {
+        adjacency[vertex.id][vertex.id] = label_weight(&(vertex.label, vertex.vertex_type));
+        for edge in vertex.edges.iter() {
+            if edge.to != edge.from {
+                let weight = label_weight(&edge.e_label);
+                adjacency[edge.from][edge.to] += weight;
+                adjacency[edge.to][edge.from] += weight;
+            }
+        }
+    }
+    let matrix = match matrix {
+        SpectralMatrix::Adjacency => adjacency,
+        SpectralMatrix::NormalizedLaplacian => {
+            // I - D^(-1/2) A D^(-1/2); the degrees include the vertex weights, so they are > 0
+            let inverse_sqrt_degrees: Vec<f64> = adjacency
+                .iter()
+                .map(|row| 1.0 / row.iter().sum::<f64>().sqrt())
+                .collect();
+            (0..n)
+                .map(|i| {
+                    (0..n)
+                        .map(|j| {
+                            let identity = if i == j { 1.0 } else { 0.0 };
+                            identity
+                                - inverse_sqrt_degrees[i]
+                                    * adjacency[i][j]
+                                    * inverse_sqrt_degrees[j]
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    };
+    let mut eigenvalues = symmetric_eigenvalues(matrix);
+    eigenvalues.sort_unstable_by(|a, b| b.total_cmp(a));
+    eigenvalues
+}
+
+/// Eigenvalues of a symmetric matrix with the cyclic Jacobi eigenvalue algorithm
+fn symmetric_eigenvalues(mut matrix: Vec<Vec<f64>>) -> Vec<f64> {
+    const MAX_SWEEPS: usize = 100;
+    const EPS: f64 = 1e-12;
+    let n = matrix.len();
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| matrix[i][j] * matrix[i][j])
+            .sum();
+        if off_diagonal < EPS {
+            break;
+        }
+        for p in 0..n {
+            for q in (p + 1)..n {
+                if matrix[p][q].abs() < EPS {
+                    continue;
+                }
+                // Rotation that zeroes the entry (p, q)
+                let theta = (matrix[q][q] - matrix[p][p]) / (2.0 * matrix[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in matrix.iter_mut() {
+                    let (a_kp, a_kq) = (row[p], row[q]);
+                    row[p] = c * a_kp - s * a_kq;
+                    row[q] = s * a_kp + c * a_kq;
+                }
+                let (upper_rows, lower_rows) = matrix.split_at_mut(q);
+                for (a_pk, a_qk) in upper_rows[p].iter_mut().zip(lower_rows[0].iter_mut()) {
+                    let (old_pk, old_qk) = (*a_pk, *a_qk);
+                    *a_pk = c * old_pk - s * old_qk;
+                    *a_qk = s * old_pk + c * old_qk;
+                }
+            }
+        }
+    }
+    (0..n).map(|i| matrix[i][i]).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::data::graph::Graph;
@@ -130,4 +375,128 @@ mod tests {
         assert_eq!(edge_vector[&(2, 2, 3, 3, 4)], 1);
         assert_eq!(edge_vector[&(2, 2, 4, 2, 2)], 2);
     }
+
+    #[test]
+    fn test_wl_features() {
+        // Path 0 -> 1 -> 2 and star 0 -> 1, 0 -> 2 with equal labels
+        let mut path = Graph::new(1);
+        let mut star = Graph::new(2);
+        for graph in [&mut path, &mut star] {
+            graph.create_vertex_with_data(1, 2);
+            graph.create_vertex_with_data(1, 2);
+            graph.create_vertex_with_data(1, 2);
+            graph.vertices.get_mut(0).unwrap().push(1, 0);
+        }
+        path.vertices.get_mut(1).unwrap().push(2, 0);
+        star.vertices.get_mut(0).unwrap().push(2, 0);
+
+        assert_eq!(build_wl_features(&path, 0), build_wl_features(&star, 0));
+        let path_features = build_wl_features(&path, 1);
+        assert_eq!(path_features.values().sum::<usize>(), 6);
+        // Start, middle, and end of the path differ after one iteration
+        assert_eq!(path_features.len(), 4);
+        assert_ne!(path_features, build_wl_features(&star, 1));
+
+        // Same structure in another vertex order
+        let mut other_path = Graph::new(3);
+        other_path.create_vertex_with_data(1, 2);
+        other_path.create_vertex_with_data(1, 2);
+        other_path.create_vertex_with_data(1, 2);
+        other_path.vertices.get_mut(2).unwrap().push(0, 0);
+        other_path.vertices.get_mut(0).unwrap().push(1, 0);
+        assert_eq!(path_features, build_wl_features(&other_path, 1));
+    }
+
+    #[test]
+    fn test_symmetric_eigenvalues() {
+        let mut eigenvalues = symmetric_eigenvalues(vec![
+            vec![2.0, 1.0, 0.0],
+            vec![1.0, 2.0, 1.0],
+            vec![0.0, 1.0, 2.0],
+        ]);
+        eigenvalues.sort_unstable_by(|a, b| b.total_cmp(a));
+        let expected = [2.0 + 2.0f64.sqrt(), 2.0, 2.0 - 2.0f64.sqrt()];
+        for (eigenvalue, expected) in eigenvalues.iter().zip(expected) {
+            assert!((eigenvalue - expected).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_invariants() {
+        let make_graph = |labels: &[usize], edges: &[(usize, usize, usize)]| {
+            let mut graph = Graph::new(1);
+            for label in labels {
+                graph.create_vertex_with_data(*label, *label % 2);
+            }
+            for (from, to, label) in edges {
+                graph.vertices.get_mut(*from).unwrap().push(*to, *label);
+            }
+            graph
+        };
+        let path = make_graph(&[1, 2, 3], &[(0, 1, 0), (1, 2, 0)]);
+        let invariants = build_invariants(&path);
+        assert_eq!(invariants.number_of_vertices, 3);
+        assert_eq!(invariants.number_of_edges, 2);
+        assert_eq!(invariants.type_counts, vec![(0, 1), (1, 2)]);
+        assert_eq!(invariants.degree_sequence, vec![(0, 1), (1, 0), (1, 1)]);
+        // Isomorphic graph with another vertex order
+        let reordered = make_graph(&[3, 1, 2], &[(1, 2, 0), (2, 0, 0)]);
+        assert_eq!(build_invariants(&reordered), invariants);
+        // Other labels, other edge label, other degrees
+        let relabeled = make_graph(&[1, 2, 5], &[(0, 1, 0), (1, 2, 0)]);
+        assert_ne!(
+            build_invariants(&relabeled).label_hash,
+            invariants.label_hash
+        );
+        let edge_relabeled = make_graph(&[1, 2, 3], &[(0, 1, 0), (1, 2, 4)]);
+        assert_ne!(build_invariants(&edge_relabeled), invariants);
+        let star = make_graph(&[1, 2, 3], &[(1, 0, 0), (1, 2, 0)]);
+        assert_ne!(
+            build_invariants(&star).degree_sequence,
+            invariants.degree_sequence
+        );
+    }
+
+    #[test]
+    fn test_spectrum() {
+        let mut path = Graph::new(1);
+        path.create_vertex_with_data(1, 2);
+        path.create_vertex_with_data(2, 2);
+        path.create_vertex_with_data(3, 2);
+        path.vertices.get_mut(0).unwrap().push(1, 0);
+        path.vertices.get_mut(1).unwrap().push(2, 0);
+        // Same path in another vertex order and edge direction
+        let mut other_path = Graph::new(2);
+        other_path.create_vertex_with_data(3, 2);
+        other_path.create_vertex_with_data(1, 2);
+        other_path.create_vertex_with_data(2, 2);
+        other_path.vertices.get_mut(2).unwrap().push(1, 0);
+        other_path.vertices.get_mut(0).unwrap().push(2, 0);
+
+        for matrix in [
+            SpectralMatrix::Adjacency,
+            SpectralMatrix::NormalizedLaplacian,
+        ] {
+            let spectrum = build_spectrum(&path, matrix);
+            assert_eq!(spectrum.len(), 3);
+            for (a, b) in spectrum.iter().zip(build_spectrum(&other_path, matrix)) {
+                assert!((a - b).abs() < 1e-8);
+            }
+        }
+        // The trace of the adjacency is the sum of the vertex weights
+        let trace: f64 = build_spectrum(&path, SpectralMatrix::Adjacency)
+            .iter()
+            .sum();
+        let weights: f64 = [1usize, 2, 3]
+            .iter()
+            .map(|l| label_weight(&(*l, 2usize)))
+            .sum();
+        assert!((trace - weights).abs() < 1e-8);
+        // The eigenvalues of the normalized Laplacian are between 0 and 2
+        assert!(
+            build_spectrum(&path, SpectralMatrix::NormalizedLaplacian)
+                .iter()
+                .all(|eigenvalue| (-1e-8..=2.0 + 1e-8).contains(eigenvalue))
+        );
+    }
 }
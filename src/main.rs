@@ -1,14 +1,26 @@
-use crate::data::graph::Graph;
-use std::time::Instant;
+use crate::data::{
+    graph::{Graph, SpectralMatrix},
+    taxonomy::Taxonomy,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 pub mod cpd;
 pub mod data;
 
 use clap::Parser;
 use cpd::{
-    candidate_generation::AlgoCandidateGeneration,
+    candidate_generation::{
+        ActivityTypeLimits, AlgoCandidateGeneration, ContextConfig, ContextSelectionPolicy,
+    },
+    candidate_matching::AlgoCandidateMatching,
     config::CPDConfig,
-    graph_matching::{AlgoGraphMatching, GEDEditCosts},
+    graph_matching::{
+        AlgoGraphMatching, CascadeStage, CascadeStats, GEDEditCosts, GEDNormalization, IdfSource,
+        IdfWeights, LabelSimilarity, SimilarityMeasure, TypeChangeCost,
+    },
 };
 
 /// Fast Rust implementation for Collaboration Pattern Detection
@@ -34,25 +46,198 @@ struct Args {
 
     /// Graph matching:
     /// - "cosine" (node and edge vector similarity, uses the alpha parameter),
-    /// - "ged" (approx. graph edit distance)
-    /// - "vf2" (only exact matches)
+    /// - "weighted-jaccard", "dice", "overlap" (like "cosine" with another similarity measure),
+    /// - "ged" (approx. graph edit distance),
+    /// - "ged-exact" (exact graph edit distance, for small candidates),
+    /// - "wl" (Weisfeiler-Lehman subtree kernel, uses the wl-iterations parameter),
+    /// - "spectral" (eigenvalue spectra, uses the spectral-matrix parameter),
+    /// - "mcs" (share of the maximum common connected subgraph, uses the mcs-time-limit
+    ///   parameter),
+    /// - "vf2" (only exact matches),
+    /// - "cascade" (chain of the --cascade-stages, VF2 only confirms exact matches),
+    /// - "context-relaxed" (exact activity core with VF2, object context with the
+    ///   --context-matching)
     #[arg(long, default_value = "cosine")]
     graph_matching: String,
 
+    /// Candidate matching:
+    /// - "parallel" (every candidate with every other candidate, in parallel),
+    /// - "naive" (every candidate with every other candidate, sequential),
+    /// - "lsh" (only candidates in the same bucket of a MinHash index, uses the lsh-bands and
+    ///   lsh-rows parameters)
+    #[arg(long, default_value = "parallel")]
+    candidate_matching: String,
+
+    /// Number of bands of the MinHash index for candidate matching "lsh"; more bands find more
+    /// relaxed matches
+    #[arg(long, default_value_t = 16)]
+    lsh_bands: usize,
+
+    /// Number of rows per band of the MinHash index for candidate matching "lsh"; more rows
+    /// compare fewer dissimilar candidates
+    #[arg(long, default_value_t = 4)]
+    lsh_rows: usize,
+
+    /// Graph matching of the object context for the graph matching "context-relaxed"; one of the
+    /// other --graph-matching options, uses the --relaxed-threshold
+    #[arg(long, default_value = "ged")]
+    context_matching: String,
+
+    /// Stages of the graph matching "cascade", in order:
+    /// - "label" (rejects on vertex and edge label multiset differences),
+    /// - "cosine" (rejects below the --cascade-cosine-threshold, uses the alpha parameter),
+    /// - "ged" (rejects above the --relaxed-threshold),
+    /// - "ged-exact" (rejects above the --relaxed-threshold)
+    #[arg(long, num_args = 1.., default_values_t = vec!["label".to_string(), "cosine".to_string(), "ged".to_string()])]
+    cascade_stages: Vec<String>,
+
+    /// Maximum number of differing vertex and edge labels for the "label" cascade stage
+    #[arg(long, default_value_t = 2)]
+    cascade_max_label_difference: usize,
+
+    /// Minimum similarity [0.0..1.0] for the "cosine" cascade stage
+    #[arg(long, default_value_t = 0.8)]
+    cascade_cosine_threshold: f64,
+
+    /// Inverse document frequency weighting of the vertex and edge vectors for the vector
+    /// similarity graph matchings ("cosine", "weighted-jaccard", "dice", "overlap", and the
+    /// "cosine" cascade stage):
+    /// - "none" (raw label counts),
+    /// - "graphs" (document frequencies over all input graphs),
+    /// - "candidates" (document frequencies over all candidates)
+    #[arg(long, default_value = "none")]
+    idf_weighting: String,
+
+    /// Number of Weisfeiler-Lehman iterations for graph matching "wl", i.e., the depth of the
+    /// compared neighbourhoods
+    #[arg(long, default_value_t = 3)]
+    wl_iterations: usize,
+
+    /// Matrix whose eigenvalues are compared by graph matching "spectral":
+    /// - "adjacency" (label-weighted adjacency matrix),
+    /// - "laplacian" (normalized Laplacian of the label-weighted adjacency matrix)
+    #[arg(long, default_value = "adjacency")]
+    spectral_matrix: String,
+
+    /// Maximum search time in milliseconds per pair for graph matching "mcs"; afterwards, the
+    /// largest common subgraph found so far is used
+    #[arg(long, default_value_t = 100)]
+    mcs_time_limit: u64,
+
     /// Relaxed threshold
-    /// - values [0.0..1.0] for graph matching "cosine" (1.0 means exact matches)
-    /// - values >= 0 for graph matching "ged" (0 means exact matches)
+    /// - values [0.0..1.0] for graph matching "cosine", "weighted-jaccard", "dice", "overlap",
+    ///   "wl", "spectral", and "mcs" (1.0 means exact matches)
+    /// - values >= 0 for graph matching "ged" and "ged-exact" (0 means exact matches), relative
+    ///   to the graph sizes with a --ged-normalization other than "absolute"
     #[arg(long, default_value_t = 0.95)]
     relaxed_threshold: f64,
 
-    /// Activity vertex type
-    #[arg(long, default_value_t = 0)]
-    activity_vertex_type: usize,
+    /// Node substitution cost for graph matching "ged" and "ged-exact"
+    #[arg(long, default_value_t = 1)]
+    ged_node_sub: usize,
+
+    /// Node insertion cost for graph matching "ged" and "ged-exact"
+    #[arg(long, default_value_t = 1)]
+    ged_node_ins: usize,
+
+    /// Node deletion cost for graph matching "ged" and "ged-exact"
+    #[arg(long, default_value_t = 1)]
+    ged_node_del: usize,
+
+    /// Edge substitution cost for graph matching "ged" and "ged-exact"
+    #[arg(long, default_value_t = 1)]
+    ged_edge_sub: usize,
+
+    /// Edge insertion cost for graph matching "ged" and "ged-exact"
+    #[arg(long, default_value_t = 1)]
+    ged_edge_ins: usize,
+
+    /// Edge deletion cost for graph matching "ged" and "ged-exact"
+    #[arg(long, default_value_t = 1)]
+    ged_edge_del: usize,
+
+    /// File with node substitution costs per vertex type ("t TYPE COST") and per vertex label
+    /// pair ("l LABEL LABEL COST") for graph matching "ged" and "ged-exact"
+    #[arg(long)]
+    ged_cost_table: Option<String>,
+
+    /// File with similarities of vertex labels ("s LABEL LABEL SIMILARITY"), e.g. of
+    /// near-synonymous activities. Reduces the node substitution costs of "ged" and "ged-exact",
+    /// and turns "cosine" into the soft cosine similarity.
+    #[arg(long)]
+    label_similarity: Option<String>,
+
+    /// File with the label hierarchy per vertex type ("p TYPE CHILD PARENT"). Candidates are
+    /// also matched with generalized labels; every pattern is reported at the most specific
+    /// level that reaches the support
+    #[arg(long)]
+    taxonomy: Option<String>,
+
+    /// Substitution of a vertex by a vertex of another type for graph matching "ged" and
+    /// "ged-exact":
+    /// - "substitution" (same costs as any other substitution),
+    /// - "forbidden" (e.g., activities are never substituted by objects),
+    /// - a number (fixed cost for every substitution across vertex types)
+    #[arg(long, default_value = "substitution")]
+    ged_type_change: String,
+
+    /// Normalization of the distance for graph matching "ged" and "ged-exact":
+    /// - "absolute" (the edit costs as is),
+    /// - "max-edit-cost" (divided by the cost of deleting one graph and inserting the other),
+    /// - "graph-size" (divided by the larger number of vertices plus edges of both graphs)
+    #[arg(long, default_value = "absolute")]
+    ged_normalization: String,
+
+    /// Candidate generation:
+    /// - "fully-connected" (connected sets of activity vertices with their object context),
+    /// - "ego-network" (neighbourhood of each object vertex of the --center-vertex-type),
+    /// - "directed-paths" (directed paths of activity vertices with their object context),
+    /// - "directed-dags" (connected, acyclic sets of activity vertices with their object context)
+    #[arg(long, default_value = "fully-connected")]
+    candidate_generation: String,
+
+    /// Vertex type of the center vertices for the "ego-network" candidate generation
+    #[arg(long)]
+    center_vertex_type: Option<usize>,
+
+    /// Radius (number of hops, ignoring the edge direction) of the "ego-network" candidates
+    #[arg(long, default_value_t = 1)]
+    radius: usize,
+
+    /// Activity vertex types; all of them together form the activity core of a candidate
+    #[arg(long, visible_alias = "activity-vertex-type", num_args = 1.., default_values_t = vec![0])]
+    activity_vertex_types: Vec<usize>,
+
+    /// Number of activity vertices per activity vertex type in a candidate in the format
+    /// TYPE:MIN or TYPE:MIN:MAX, e.g. "3:1:2" requires one or two activity vertices of type 3
+    #[arg(long, num_args = 0.., value_parser = parse_activity_type_limits)]
+    activity_type_limits: Vec<(usize, ActivityTypeLimits)>,
 
     /// Object vertex types
     #[arg(long, num_args = 0..)]
     object_vertex_types: Vec<usize>,
 
+    /// Context depth per object vertex type in the format TYPE:DEPTH, e.g. "6:2" adds object
+    /// vertices of type 6 up to two hops away from the activity vertices (default depth is 1)
+    #[arg(long, num_args = 0.., value_parser = parse_type_value)]
+    context_depths: Vec<(usize, usize)>,
+
+    /// Maximum number of context (object) vertices added to a candidate
+    #[arg(long)]
+    max_context_vertices: Option<usize>,
+
+    /// Maximum number of context vertices per object vertex type in the format TYPE:MAX
+    #[arg(long, num_args = 0.., value_parser = parse_type_value)]
+    max_context_vertices_per_type: Vec<(usize, usize)>,
+
+    /// Selection of context vertices with the same distance to the activity vertices:
+    /// - "nearest" (in the order they are found),
+    /// - "most-shared" (objects connected to the most activities first),
+    /// - "rare-labels" (objects with rare labels across all input graphs first),
+    /// - "shared-only" (drop objects connected to only one activity)
+    #[arg(long, default_value = "nearest")]
+    context_selection: String,
+
     /// Minimum number of main vertices
     #[arg(long, default_value_t = 4)]
     min_vertices: usize,
@@ -61,6 +246,11 @@ struct Args {
     #[arg(long, default_value_t = 5)]
     max_vertices: usize,
 
+    /// Non-induced candidates: additionally use all connected spanning edge subsets of a candidate
+    /// that drop up to this number of edges (0 = only vertex-induced candidates)
+    #[arg(long, default_value_t = 0)]
+    max_dropped_edges: usize,
+
     /// The alpha value between 0.0 and 1.0 defines the weight importance of the vertex and edge
     /// vectors: if 1.0, the edges are ignored; if 0.0, the vertices are ignored
     #[arg(long, default_value_t = 0.5)]
@@ -77,6 +267,41 @@ struct Args {
     silence: bool,
 }
 
+/// Parses a "KEY:VALUE" pair of two unsigned integers
+fn parse_type_value(s: &str) -> Result<(usize, usize), String> {
+    let (key, value) = s
+        .split_once(':')
+        .ok_or(format!("invalid KEY:VALUE pair: no ':' found in '{s}'"))?;
+    let key = key
+        .parse::<usize>()
+        .map_err(|e| format!("invalid key '{key}': {e}"))?;
+    let value = value
+        .parse::<usize>()
+        .map_err(|e| format!("invalid value '{value}': {e}"))?;
+    Ok((key, value))
+}
+
+/// Parses a "TYPE:MIN" or "TYPE:MIN:MAX" activity type limit
+fn parse_activity_type_limits(s: &str) -> Result<(usize, ActivityTypeLimits), String> {
+    let mut parts = s.split(':');
+    let mut next_number = |name: &str| -> Result<Option<usize>, String> {
+        parts
+            .next()
+            .map(|part| {
+                part.parse::<usize>()
+                    .map_err(|e| format!("invalid {name} '{part}': {e}"))
+            })
+            .transpose()
+    };
+    let vertex_type = next_number("type")?.ok_or("missing type")?;
+    let min = next_number("min")?.ok_or(format!("missing ':MIN' in '{s}'"))?;
+    let max = next_number("max")?;
+    if next_number("value")?.is_some() {
+        return Err(format!("too many values in '{s}'"));
+    }
+    Ok((vertex_type, ActivityTypeLimits { min, max }))
+}
+
 fn main() {
     let args = Args::parse();
     let silence = args.silence;
@@ -93,29 +318,154 @@ fn main() {
         );
         return;
     }
-    if args.graph_matching == "cosine" && (args.alpha > 1.0 || args.alpha < 0.0) {
+    if let Some((vertex_type, limits)) = args
+        .activity_type_limits
+        .iter()
+        .find(|(_, limits)| limits.max.is_some_and(|max| limits.min > max))
+    {
+        eprintln!(
+            "Parameter error! Min number of activity vertices of type {} ({}) > Max number ({})!",
+            vertex_type,
+            limits.min,
+            limits.max.unwrap()
+        );
+        return;
+    }
+    // The context-relaxed matcher wraps the --context-matching
+    let context_relaxed = args.graph_matching == "context-relaxed";
+    let graph_matching_name = if context_relaxed {
+        args.context_matching.clone()
+    } else {
+        args.graph_matching.clone()
+    };
+    let similarity_measure = match graph_matching_name.as_str() {
+        "cosine" => Some(SimilarityMeasure::Cosine),
+        "weighted-jaccard" => Some(SimilarityMeasure::WeightedJaccard),
+        "dice" => Some(SimilarityMeasure::Dice),
+        "overlap" => Some(SimilarityMeasure::Overlap),
+        _ => None,
+    };
+    if (similarity_measure.is_some() || graph_matching_name == "cascade")
+        && (args.alpha > 1.0 || args.alpha < 0.0)
+    {
         eprintln!(
             "Parameter error! --alpha should be 0.0 <= alpha <= 1.0, is {}",
             args.alpha
         );
         return;
     }
-    if args.graph_matching == "cosine"
+    if (similarity_measure.is_some()
+        || matches!(graph_matching_name.as_str(), "wl" | "spectral" | "mcs"))
         && (args.relaxed_threshold > 1.0 || args.relaxed_threshold < 0.0)
     {
         eprintln!(
-            "Parameter error! for cosine graph matchting, the --relaxed-threshold should be 0.0 <= relaxed_threshold <= 1.0, is {}",
+            "Parameter error! for vector similarity, wl, spectral, and mcs graph matchting, the --relaxed-threshold should be 0.0 <= relaxed_threshold <= 1.0, is {}",
             args.relaxed_threshold
         );
         return;
     }
-    if args.graph_matching == "ged" && args.relaxed_threshold < 0.0 {
+    if graph_matching_name == "cascade"
+        && (args.cascade_cosine_threshold > 1.0 || args.cascade_cosine_threshold < 0.0)
+    {
+        eprintln!(
+            "Parameter error! --cascade-cosine-threshold should be 0.0 <= threshold <= 1.0, is {}",
+            args.cascade_cosine_threshold
+        );
+        return;
+    }
+    if matches!(
+        graph_matching_name.as_str(),
+        "ged" | "ged-exact" | "cascade"
+    ) && args.relaxed_threshold < 0.0
+    {
         eprintln!(
             "Parameter error! for ged graph matchting, the --relaxed-threshold should be >= 0, is {}",
             args.relaxed_threshold
         );
         return;
     }
+    let candidate_matching = match args.candidate_matching.as_str() {
+        "parallel" => AlgoCandidateMatching::Parallel,
+        "naive" => AlgoCandidateMatching::Naive,
+        "lsh" => {
+            if args.lsh_bands == 0 || args.lsh_rows == 0 {
+                eprintln!(
+                    "Parameter error! --lsh-bands ({}) and --lsh-rows ({}) should be >= 1",
+                    args.lsh_bands, args.lsh_rows
+                );
+                return;
+            }
+            AlgoCandidateMatching::MinHashLsh {
+                bands: args.lsh_bands,
+                rows: args.lsh_rows,
+            }
+        }
+        other => {
+            eprintln!(
+                "Parameter error! Unknown --candidate-matching {other}, should be one of: parallel, naive, lsh"
+            );
+            return;
+        }
+    };
+    let selection_policy = match args.context_selection.as_str() {
+        "nearest" => ContextSelectionPolicy::Nearest,
+        "most-shared" => ContextSelectionPolicy::MostShared,
+        "rare-labels" => ContextSelectionPolicy::RareLabelsFirst,
+        "shared-only" => ContextSelectionPolicy::SharedOnly,
+        other => {
+            eprintln!(
+                "Parameter error! Unknown --context-selection {other}, should be one of: nearest, most-shared, rare-labels, shared-only"
+            );
+            return;
+        }
+    };
+    let context = ContextConfig {
+        depths: args.context_depths.into_iter().collect(),
+        max_vertices: args.max_context_vertices,
+        max_vertices_per_type: args.max_context_vertices_per_type.into_iter().collect(),
+        selection_policy,
+    };
+    let candidate_generation = match args.candidate_generation.as_str() {
+        "fully-connected" => AlgoCandidateGeneration::FullyConnected {
+            activity_vertex_types: args.activity_vertex_types.clone(),
+            activity_type_limits: args.activity_type_limits.into_iter().collect(),
+            object_vertex_types: args.object_vertex_types,
+            context,
+            min_number_of_activity_vertices: args.min_vertices,
+            max_number_of_activity_vertices: args.max_vertices,
+            max_dropped_edges: args.max_dropped_edges,
+        },
+        "ego-network" => {
+            let Some(center_vertex_type) = args.center_vertex_type else {
+                eprintln!(
+                    "Parameter error! --center-vertex-type is required for the ego-network candidate generation"
+                );
+                return;
+            };
+            AlgoCandidateGeneration::EgoNetwork {
+                center_vertex_type,
+                activity_vertex_types: args.activity_vertex_types.clone(),
+                object_vertex_types: args.object_vertex_types,
+                radius: args.radius,
+                min_number_of_activity_vertices: args.min_vertices,
+                max_number_of_activity_vertices: args.max_vertices,
+            }
+        }
+        "directed-paths" | "directed-dags" => AlgoCandidateGeneration::DirectedPaths {
+            activity_vertex_types: args.activity_vertex_types.clone(),
+            object_vertex_types: args.object_vertex_types,
+            context,
+            min_number_of_activity_vertices: args.min_vertices,
+            max_number_of_activity_vertices: args.max_vertices,
+            dags: args.candidate_generation == "directed-dags",
+        },
+        other => {
+            eprintln!(
+                "Parameter error! Unknown --candidate-generation {other}, should be one of: fully-connected, ego-network, directed-paths, directed-dags"
+            );
+            return;
+        }
+    };
     let now = Instant::now();
     let graphs = Graph::graphs_set_from_file(args.input);
     let graphs = match graphs {
@@ -130,32 +480,180 @@ fn main() {
             return;
         }
     };
-    let mut graph_matching = AlgoGraphMatching::GEDFastHungarian {
-        edit_costs: GEDEditCosts::default(),
-        matching_threshold: args.relaxed_threshold.round() as usize,
+    let type_change = match args.ged_type_change.as_str() {
+        "substitution" => TypeChangeCost::NodeSubstitution,
+        "forbidden" => TypeChangeCost::Forbidden,
+        other => match other.parse::<usize>() {
+            Ok(cost) => TypeChangeCost::Cost(cost),
+            Err(_) => {
+                eprintln!(
+                    "Parameter error! Unknown --ged-type-change {other}, should be one of: substitution, forbidden, or a number"
+                );
+                return;
+            }
+        },
     };
-    if args.graph_matching == "cosine" {
-        graph_matching = AlgoGraphMatching::CosineSimilarity {
-            alpha: args.alpha,
-            matching_threshold: args.relaxed_threshold,
-        }
+    let label_similarity = match args.label_similarity {
+        Some(path) => match LabelSimilarity::from_file(path) {
+            Ok(label_similarity) => Some(Arc::new(label_similarity)),
+            Err(err) => {
+                eprintln!("Error parsing label similarity file: {}", err);
+                return;
+            }
+        },
+        None => None,
+    };
+    let mut edit_costs = GEDEditCosts {
+        node_sub: args.ged_node_sub,
+        node_ins: args.ged_node_ins,
+        node_del: args.ged_node_del,
+        edge_sub: args.ged_edge_sub,
+        edge_ins: args.ged_edge_ins,
+        edge_del: args.ged_edge_del,
+        type_change,
+        label_similarity: label_similarity.clone(),
+        ..Default::default()
+    };
+    if let Some(ged_cost_table) = args.ged_cost_table
+        && let Err(err) = edit_costs.read_substitution_costs(ged_cost_table)
+    {
+        eprintln!("Error parsing GED cost table: {}", err);
+        return;
     }
-    if args.graph_matching == "vf2" {
-        graph_matching = AlgoGraphMatching::VF2IsomorphismTest;
+    let normalization = match args.ged_normalization.as_str() {
+        "absolute" => GEDNormalization::Absolute,
+        "max-edit-cost" => GEDNormalization::MaxEditCost,
+        "graph-size" => GEDNormalization::GraphSize,
+        other => {
+            eprintln!(
+                "Parameter error! Unknown --ged-normalization {other}, should be one of: absolute, max-edit-cost, graph-size"
+            );
+            return;
+        }
+    };
+    // Absolute GEDs are integers, so a GED within the threshold is within its integer part
+    let ged_threshold = if normalization == GEDNormalization::Absolute {
+        args.relaxed_threshold.floor()
+    } else {
+        args.relaxed_threshold
+    };
+    let idf_source = match args.idf_weighting.as_str() {
+        "none" => None,
+        "graphs" => Some(IdfSource::Graphs),
+        "candidates" => Some(IdfSource::Candidates),
+        other => {
+            eprintln!(
+                "Parameter error! Unknown --idf-weighting {other}, should be one of: none, graphs, candidates"
+            );
+            return;
+        }
+    };
+    let spectral_matrix = match args.spectral_matrix.as_str() {
+        "adjacency" => SpectralMatrix::Adjacency,
+        "laplacian" => SpectralMatrix::NormalizedLaplacian,
+        other => {
+            eprintln!(
+                "Parameter error! Unknown --spectral-matrix {other}, should be one of: adjacency, laplacian"
+            );
+            return;
+        }
+    };
+    let mut cascade_stages = Vec::new();
+    if graph_matching_name == "cascade" {
+        for stage in args.cascade_stages.iter() {
+            cascade_stages.push(match stage.as_str() {
+                "label" => CascadeStage::LabelBound {
+                    max_label_difference: args.cascade_max_label_difference,
+                    edit_costs: edit_costs.clone(),
+                },
+                "cosine" => CascadeStage::Matcher(AlgoGraphMatching::CosineSimilarity {
+                    measure: SimilarityMeasure::Cosine,
+                    alpha: args.alpha,
+                    matching_threshold: args.cascade_cosine_threshold,
+                    idf: idf_source.map(IdfWeights::new),
+                    label_similarity: label_similarity.clone(),
+                }),
+                "ged" => CascadeStage::Matcher(AlgoGraphMatching::GEDFastHungarian {
+                    edit_costs: edit_costs.clone(),
+                    normalization,
+                    matching_threshold: ged_threshold,
+                }),
+                "ged-exact" => CascadeStage::Matcher(AlgoGraphMatching::GEDExact {
+                    edit_costs: edit_costs.clone(),
+                    normalization,
+                    matching_threshold: ged_threshold,
+                }),
+                other => {
+                    eprintln!(
+                        "Parameter error! Unknown --cascade-stages {other}, should be one of: label, cosine, ged, ged-exact"
+                    );
+                    return;
+                }
+            });
+        }
     }
-    let cpd_config = CPDConfig::new(
-        AlgoCandidateGeneration::FullyConnected {
-            activity_vertex_type: args.activity_vertex_type,
-            object_vertex_types: args.object_vertex_types,
-            min_number_of_activity_vertices: args.min_vertices,
-            max_number_of_activity_vertices: args.max_vertices,
+    let graph_matching = match graph_matching_name.as_str() {
+        "cosine" | "weighted-jaccard" | "dice" | "overlap" => AlgoGraphMatching::CosineSimilarity {
+            measure: similarity_measure.unwrap(),
+            alpha: args.alpha,
+            matching_threshold: args.relaxed_threshold,
+            idf: idf_source.map(IdfWeights::new),
+            label_similarity,
+        },
+        "wl" => AlgoGraphMatching::WLSubtreeKernel {
+            iterations: args.wl_iterations,
+            matching_threshold: args.relaxed_threshold,
         },
+        "spectral" => AlgoGraphMatching::Spectral {
+            matrix: spectral_matrix,
+            matching_threshold: args.relaxed_threshold,
+        },
+        "mcs" => AlgoGraphMatching::MaximumCommonSubgraph {
+            time_limit: Duration::from_millis(args.mcs_time_limit),
+            matching_threshold: args.relaxed_threshold,
+        },
+        "vf2" => AlgoGraphMatching::VF2IsomorphismTest,
+        "cascade" => AlgoGraphMatching::Cascade {
+            stats: CascadeStats::new(cascade_stages.len()),
+            stages: cascade_stages,
+        },
+        "ged-exact" => AlgoGraphMatching::GEDExact {
+            edit_costs,
+            normalization,
+            matching_threshold: ged_threshold,
+        },
+        _ => AlgoGraphMatching::GEDFastHungarian {
+            edit_costs,
+            normalization,
+            matching_threshold: ged_threshold,
+        },
+    };
+    let graph_matching = if context_relaxed {
+        AlgoGraphMatching::ContextRelaxed {
+            activity_vertex_types: args.activity_vertex_types,
+            context_matching: Box::new(graph_matching),
+        }
+    } else {
+        graph_matching
+    };
+    let mut cpd_config = CPDConfig::new(
+        candidate_generation,
         graph_matching,
         args.support_exact,
         args.support_relaxed,
         args.silence,
         args.compare_only_same_size,
     );
+    cpd_config.set_candidate_matching(candidate_matching);
+    if let Some(taxonomy) = args.taxonomy {
+        match Taxonomy::from_file(taxonomy) {
+            Ok(taxonomy) => cpd_config.set_taxonomy(taxonomy),
+            Err(err) => {
+                eprintln!("Error parsing taxonomy file: {}", err);
+                return;
+            }
+        }
+    }
     if !silence {
         println!("Mining patterns..");
     };
@@ -167,11 +665,7 @@ fn main() {
     };
     if args.output == "stdout" {
         for g in patterns.iter() {
-            println!(
-                "{}",
-                g.pattern
-                    .to_str_repr(Some(g.frequency_exact), Some(g.frequency_relaxed))
-            );
+            println!("{}", g.to_str_repr());
         }
     } else {
         use std::fs::File;
@@ -185,11 +679,7 @@ fn main() {
         let mut file = file.unwrap();
 
         for g in patterns.iter() {
-            let line = format!(
-                "{}\n",
-                g.pattern
-                    .to_str_repr(Some(g.frequency_exact), Some(g.frequency_relaxed))
-            );
+            let line = format!("{}\n", g.to_str_repr());
             file.write_all(line.as_bytes())
                 .expect("Failed to write to output file");
         }